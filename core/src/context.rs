@@ -21,6 +21,32 @@ pub enum CreateScheme {
 	Fixed(H160),
 }
 
+impl CreateScheme {
+	/// Address of the contract created under this scheme, given the `nonce`
+	/// of the caller before the create and a Keccak-256 hasher: the hash of
+	/// `rlp([caller, nonce])` for `CREATE`, and of
+	/// `0xff ++ caller ++ salt ++ code_hash` for `CREATE2` (EIP-1014).
+	pub fn address<F: Fn(&[u8]) -> H256>(&self, nonce: U256, keccak: F) -> H160 {
+		match self {
+			Self::Legacy { caller } => {
+				let mut stream = rlp::RlpStream::new_list(2);
+				stream.append(caller);
+				stream.append(&nonce);
+				keccak(&stream.out()).into()
+			},
+			Self::Create2 { caller, code_hash, salt } => {
+				let mut preimage = [0_u8; 85];
+				preimage[0] = 0xff;
+				preimage[1..21].copy_from_slice(caller.as_bytes());
+				preimage[21..53].copy_from_slice(salt.as_bytes());
+				preimage[53..85].copy_from_slice(code_hash.as_bytes());
+				keccak(&preimage).into()
+			},
+			Self::Fixed(address) => *address,
+		}
+	}
+}
+
 /// Call scheme.
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum CallScheme {
@@ -46,6 +72,11 @@ pub struct Context {
 	pub caller: H160,
 	/// Apparent value of the EVM.
 	pub apparent_value: U256,
+	/// Call depth of the frame, zero for the outermost one.
+	pub depth: usize,
+	/// Whether the frame runs in static mode, entered by `STATICCALL` and
+	/// inherited by every frame below it (EIP-214).
+	pub is_static: bool,
 }
 
 
@@ -61,3 +92,44 @@ pub struct Transfer {
 	/// Transfer value.
 	pub value: U256,
 }
+
+#[cfg(test)]
+mod tests {
+	use core::str::FromStr;
+	use sha3::{Digest, Keccak256};
+	use crate::{CreateScheme, H160, H256, U256};
+
+	fn keccak(data: &[u8]) -> H256 {
+		H256::from_slice(Keccak256::digest(data).as_slice())
+	}
+
+	#[test]
+	fn test_create_address() {
+		let caller = H160::from_str("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+		let scheme = CreateScheme::Legacy { caller };
+
+		assert_eq!(
+			scheme.address(U256::zero(), keccak),
+			H160::from_str("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d").unwrap(),
+		);
+		assert_eq!(
+			scheme.address(U256::one(), keccak),
+			H160::from_str("343c43a37d37dff08ae8c4a11544c718abb4fcf8").unwrap(),
+		);
+	}
+
+	#[test]
+	fn test_create2_address() {
+		// Example 0 of EIP-1014.
+		let scheme = CreateScheme::Create2 {
+			caller: H160::zero(),
+			code_hash: keccak(&[0x00]),
+			salt: H256::zero(),
+		};
+
+		assert_eq!(
+			scheme.address(U256::zero(), keccak),
+			H160::from_str("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38").unwrap(),
+		);
+	}
+}
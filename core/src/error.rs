@@ -144,7 +144,15 @@ pub enum ExitError {
 	/// transfer is not allowed
 	TransferProhibited,
 	/// Delegate call id prohibited for ExtCall
-	DelegateCallProhibited
+	DelegateCallProhibited,
+	/// The opcode is not enabled by the active configuration.
+	InvalidCode(u8),
+	/// Deployed code starts with the reserved 0xEF byte (EIP-3541).
+	CreateContractStartingWithEF,
+	/// The input of a precompile is malformed or out of bounds.
+	InvalidPrecompileInput,
+	/// The creator nonce cannot be incremented any more (EIP-2681).
+	MaxNonce,
 }
 
 impl From<ExitError> for ExitReason {
@@ -580,5 +580,9 @@ pub fn eval(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
 		table
 	};
 
+	if state.disabled_opcodes.contains(opcode) {
+		return Control::Exit(ExitError::InvalidCode(opcode.0).into())
+	}
+
 	TABLE[opcode.as_usize()](state, opcode, position)
 }
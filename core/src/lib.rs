@@ -25,14 +25,16 @@ mod eval;
 mod utils;
 mod primitive_types;
 mod context;
+mod step;
 
 pub use crate::memory::Memory;
 pub use crate::stack::Stack;
 pub use crate::valids::Valids;
-pub use crate::opcode::Opcode;
+pub use crate::opcode::{Opcode, OpcodeSet};
 pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
 pub use crate::primitive_types::{H160, H256, U256, U512};
 pub use crate::context::{Context, CreateScheme, CallScheme, Transfer};
+pub use crate::step::StepCosts;
 
 use alloc::vec::Vec;
 use crate::eval::{eval, Control};
@@ -58,6 +60,8 @@ pub struct Machine {
 	memory: Memory,
 	/// Stack.
 	stack: Stack,
+	/// Opcodes disabled by the active configuration.
+	disabled_opcodes: OpcodeSet,
 }
 
 impl Machine {
@@ -85,6 +89,19 @@ impl Machine {
 		data: Vec<u8>,
 		stack_limit: usize,
 		memory_limit: usize
+	) -> Self {
+		Self::new_with_disabled_opcodes(code, valids, data, stack_limit, memory_limit, OpcodeSet::new())
+	}
+
+	/// Create a new machine that treats `disabled_opcodes` as invalid.
+	#[must_use]
+	pub fn new_with_disabled_opcodes(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		stack_limit: usize,
+		memory_limit: usize,
+		disabled_opcodes: OpcodeSet,
 	) -> Self {
 		let valids = Valids::new(valids);
 
@@ -96,9 +113,16 @@ impl Machine {
 			valids,
 			memory: Memory::new(memory_limit),
 			stack: Stack::new(stack_limit),
+			disabled_opcodes,
 		}
 	}
 
+	/// Opcodes disabled by the active configuration.
+	#[must_use]
+	pub const fn disabled_opcodes(&self) -> OpcodeSet {
+		self.disabled_opcodes
+	}
+
 	/// Explicit exit of the machine. Further step will return error.
 	pub fn exit(&mut self, reason: ExitReason) {
 		self.position = Err(reason);
@@ -129,34 +153,61 @@ impl Machine {
 		)
 	}
 
-	/// Loop stepping the machine, until it stops.
+	/// Loop stepping the machine, until it stops or the budget of
+	/// `max_steps` runs out under the default `StepCosts`.
 	pub fn run<F>(&mut self,
 				  max_steps: u64,
+				  pre_validate: F,
+				  context : &Context
+	) -> (u64, Capture<ExitReason, Trap>)
+		where F: FnMut(Opcode, &Stack) -> Result<(), ExitError>
+	{
+		self.run_with_step_costs(max_steps, &StepCosts::DEFAULT, pre_validate, context)
+	}
+
+	/// Loop stepping the machine, until it stops or the weights of the
+	/// executed opcodes under `step_costs` would exceed `max_steps`. The
+	/// first opcode always runs, so that the machine makes progress even
+	/// if it alone weighs more than the budget.
+	///
+	/// Returns the weight of the executed opcodes.
+	pub fn run_with_step_costs<F>(&mut self,
+				  max_steps: u64,
+				  step_costs: &StepCosts,
 				  mut pre_validate: F,
 				  _context : &Context
 	) -> (u64, Capture<ExitReason, Trap>)
 		where F: FnMut(Opcode, &Stack) -> Result<(), ExitError>
 	{
-		for step in 0..max_steps {
+		let mut steps = 0_u64;
+
+		while steps < max_steps {
 			let position = match self.position {
 				Ok(position) => position,
-				Err(reason) => return (step, Capture::Exit(reason))
+				Err(reason) => return (steps, Capture::Exit(reason))
 			};
 
 			let opcode = match self.code.get(position) {
 				Some(opcode) => Opcode(*opcode),
 				None => {
 					self.position = Err(ExitReason::Succeed(ExitSucceed::Stopped));
-					return (step, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped)));
+					return (steps, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped)));
 				}
 			};
 
+			let cost = step_costs.cost(opcode, &self.stack, &self.memory);
+			if steps > 0 && steps.saturating_add(cost) > max_steps {
+				break
+			}
+
 			if let Err(error) = pre_validate(opcode, &self.stack()) {
 				let reason = ExitReason::from(error);
 				self.exit(reason);
-				return (step, Capture::Exit(reason));
+				return (steps, Capture::Exit(reason));
 			}
 
+			steps = steps.saturating_add(cost);
+
 			let result = match eval(self, opcode, position) {
 				Control::Continue(p) => {
 					self.position = Ok(position + p);
@@ -177,11 +228,11 @@ impl Machine {
 			};
 
 			if let Err(capture) = result {
-				return (step, capture)
+				return (steps, capture)
 			}
 		}
 
-		(max_steps, Capture::Exit(ExitReason::StepLimitReached))
+		(steps, Capture::Exit(ExitReason::StepLimitReached))
 	}
 
 }
@@ -56,33 +56,50 @@ impl Memory {
 
 	/// Resize the memory, making it cover the memory region of `offset..(offset
 	/// + len)`, with 32 bytes as the step. If the length is zero, this function
-	/// does nothing.
-	pub fn resize_offset(&mut self, offset: usize, len: usize) -> Result<(), ExitError> {
+	/// does nothing. Returns the number of 32-byte words the memory grew by.
+	pub fn resize_offset(&mut self, offset: usize, len: usize) -> Result<usize, ExitError> {
 		if len == 0 {
-			return Ok(())
+			return Ok(0)
 		}
 
 		offset.checked_add(len).map_or(Err(ExitError::InvalidRange), |end| self.resize_end(end))
 	}
 
 	/// Resize the memory, making it cover to `end`, with 32 bytes as the step.
-	pub fn resize_end(&mut self, end: usize) -> Result<(), ExitError> {
-		let end = {
-			let modulo = end % 32;
-			if modulo == 0 {
-				end
-			} else {
-				// next closest value to `end` that is divisible by 32
-				// end = (end + 32) - (end % 32)
-				match end.checked_add(32) {
-					Some(end) => end - modulo,
-					None => return Err(ExitError::InvalidRange)
-				}
-			}
-		};
+	/// Returns the number of 32-byte words the memory grew by.
+	pub fn resize_end(&mut self, end: usize) -> Result<usize, ExitError> {
+		let words = self.expansion_words(end)?;
 
-		self.effective_len = max(self.effective_len, end);
-		Ok(())
+		self.effective_len = max(self.effective_len, Self::word_aligned(end)?);
+		Ok(words)
+	}
+
+	/// Number of 32-byte words that `resize_end` would grow the memory by to
+	/// cover `end`, without resizing it. A gas model can price the expansion
+	/// with this before it is committed.
+	pub fn expansion_words(&self, end: usize) -> Result<usize, ExitError> {
+		let end = Self::word_aligned(end)?;
+		let current = self.effective_len.div_ceil(32) * 32;
+
+		if end > current {
+			Ok((end - current) / 32)
+		} else {
+			Ok(0)
+		}
+	}
+
+	/// Next closest value to `end` that is divisible by 32.
+	const fn word_aligned(end: usize) -> Result<usize, ExitError> {
+		let modulo = end % 32;
+		if modulo == 0 {
+			Ok(end)
+		} else {
+			// end = (end + 32) - (end % 32)
+			match end.checked_add(32) {
+				Some(end) => Ok(end - modulo),
+				None => Err(ExitError::InvalidRange)
+			}
+		}
 	}
 
 	/// Get memory region at given offset.
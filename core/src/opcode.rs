@@ -1,4 +1,32 @@
 #![allow(clippy::use_self)]
+/// Set of opcodes, stored as a 256-bit map.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct OpcodeSet([u8; 32]);
+
+impl OpcodeSet {
+	/// Create an empty set.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self([0; 32])
+	}
+
+	/// The set with `opcode` added.
+	#[must_use]
+	pub const fn with(mut self, opcode: Opcode) -> Self {
+		self.0[(opcode.0 / 8) as usize] |= 1 << (opcode.0 % 8);
+		self
+	}
+
+	/// Whether `opcode` is in the set.
+	#[must_use]
+	pub const fn contains(&self, opcode: Opcode) -> bool {
+		self.0[(opcode.0 / 8) as usize] & (1 << (opcode.0 % 8)) != 0
+	}
+}
+
 /// Opcode enum. One-to-one corresponding to an `u8` value.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Opcode(pub u8);
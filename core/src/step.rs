@@ -0,0 +1,199 @@
+use crate::{Memory, Opcode, Stack, U256};
+
+/// Weights of opcodes against the step budget of `Machine::run`.
+///
+/// An opcode weighs its static weight, plus a weight for every 32-byte word
+/// it grows the memory by, copies or hashes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StepCosts {
+	/// Static weight of each opcode.
+	pub opcodes: [u64; 256],
+	/// Weight per word of memory expansion.
+	pub memory_word: u64,
+	/// Weight per word copied by `CALLDATACOPY`, `CODECOPY`, `EXTCODECOPY`,
+	/// `RETURNDATACOPY` and `MCOPY`.
+	pub copy_word: u64,
+	/// Weight per word hashed by `SHA3` and `CREATE2`.
+	pub hash_word: u64,
+}
+
+impl StepCosts {
+	/// Every opcode weighs one step, so that the budget is an instruction
+	/// count.
+	pub const FLAT: Self = Self {
+		opcodes: [1; 256],
+		memory_word: 0,
+		copy_word: 0,
+		hash_word: 0,
+	};
+
+	/// Default weights, relative to the cost of an `ADD`.
+	pub const DEFAULT: Self = {
+		let mut opcodes = [1; 256];
+
+		opcodes[Opcode::DIV.as_usize()] = 2;
+		opcodes[Opcode::SDIV.as_usize()] = 2;
+		opcodes[Opcode::MOD.as_usize()] = 2;
+		opcodes[Opcode::SMOD.as_usize()] = 2;
+		opcodes[Opcode::ADDMOD.as_usize()] = 3;
+		opcodes[Opcode::MULMOD.as_usize()] = 3;
+		opcodes[Opcode::EXP.as_usize()] = 4;
+		opcodes[Opcode::SHA3.as_usize()] = 4;
+
+		Self {
+			opcodes,
+			memory_word: 1,
+			copy_word: 1,
+			hash_word: 1,
+		}
+	};
+
+	/// Weight of executing `opcode` with the given stack and memory.
+	#[must_use]
+	pub fn cost(&self, opcode: Opcode, stack: &Stack, memory: &Memory) -> u64 {
+		let peek_words = |no_from_top| stack.peek(no_from_top).map_or(0, words);
+
+		let copied_words = match opcode {
+			Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY | Opcode::MCOPY => peek_words(2),
+			Opcode::EXTCODECOPY => peek_words(3),
+			_ => 0,
+		};
+		let hashed_words = match opcode {
+			Opcode::SHA3 => peek_words(1),
+			Opcode::CREATE2 => peek_words(2),
+			_ => 0,
+		};
+		let expansion_words = memory_end(opcode, stack).map_or(0, |end| {
+			if end > U256::from(usize::MAX) {
+				u64::MAX
+			} else {
+				memory.expansion_words(end.as_usize()).map_or(u64::MAX, |words| words as u64)
+			}
+		});
+
+		self.opcodes[opcode.as_usize()]
+			.saturating_add(expansion_words.saturating_mul(self.memory_word))
+			.saturating_add(copied_words.saturating_mul(self.copy_word))
+			.saturating_add(hashed_words.saturating_mul(self.hash_word))
+	}
+}
+
+impl Default for StepCosts {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+/// Number of 32-byte words covering `len` bytes.
+fn words(len: U256) -> u64 {
+	if len > U256::from(u64::MAX) {
+		u64::MAX
+	} else {
+		len.as_u64().div_ceil(32)
+	}
+}
+
+/// End of the memory region touched by `opcode`, if it touches any.
+fn memory_end(opcode: Opcode, stack: &Stack) -> Option<U256> {
+	let end = |offset, len| -> Option<U256> {
+		let len = stack.peek(len).ok()?;
+		if len.is_zero() {
+			return None
+		}
+		Some(stack.peek(offset).ok()?.saturating_add(len))
+	};
+	let fixed_end = |offset, len: u64| stack.peek(offset).ok().map(|offset| offset.saturating_add(len.into()));
+
+	match opcode {
+		Opcode::SHA3 | Opcode::RETURN | Opcode::REVERT
+		| Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => end(0, 1),
+		Opcode::CODECOPY | Opcode::CALLDATACOPY | Opcode::RETURNDATACOPY => end(0, 2),
+		Opcode::MCOPY => end(0, 2).max(end(1, 2)),
+		Opcode::EXTCODECOPY => end(1, 3),
+		Opcode::MLOAD | Opcode::MSTORE => fixed_end(0, 32),
+		Opcode::MSTORE8 => fixed_end(0, 1),
+		Opcode::CREATE | Opcode::CREATE2 => end(1, 2),
+		Opcode::CALL | Opcode::CALLCODE => end(3, 4).max(end(5, 6)),
+		Opcode::DELEGATECALL | Opcode::STATICCALL => end(2, 3).max(end(4, 5)),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::StepCosts;
+	use crate::{Capture, Context, ExitReason, ExitSucceed, Machine, Memory, Opcode, Stack, H160, U256};
+
+	/// Stack holding `values`, the first one on top.
+	fn stack(values: &[u64]) -> Stack {
+		let mut stack = Stack::new(1024);
+		for value in values.iter().rev() {
+			stack.push_u256(U256::from(*value)).unwrap();
+		}
+		stack
+	}
+
+	/// Run `machine` with a budget of `max_steps` weighted by `step_costs`.
+	fn run(machine: &mut Machine, max_steps: u64, step_costs: &StepCosts) -> (u64, Option<ExitReason>) {
+		let context = Context {
+			address: H160::zero(),
+			caller: H160::zero(),
+			apparent_value: U256::zero(),
+			depth: 0,
+			is_static: false,
+		};
+		match machine.run_with_step_costs(max_steps, step_costs, |_, _| Ok(()), &context) {
+			(steps, Capture::Exit(reason)) => (steps, Some(reason)),
+			(steps, Capture::Trap(_)) => (steps, None),
+		}
+	}
+
+	#[test]
+	fn test_weights() {
+		let memory = Memory::new(1024);
+		let flat = &StepCosts::FLAT;
+		let default = &StepCosts::DEFAULT;
+
+		assert_eq!(default.cost(Opcode::ADD, &stack(&[]), &memory), 1);
+		assert_eq!(default.cost(Opcode::DIV, &stack(&[]), &memory), 2);
+		assert_eq!(default.cost(Opcode::MULMOD, &stack(&[]), &memory), 3);
+		assert_eq!(default.cost(Opcode::EXP, &stack(&[]), &memory), 4);
+
+		// SHA3 of 64 bytes at offset 0: static weight, two words hashed and
+		// two words of memory expansion.
+		assert_eq!(default.cost(Opcode::SHA3, &stack(&[0, 64]), &memory), 4 + 2 + 2);
+		// CALLDATACOPY of 64 bytes to offset 32: two words copied, three of
+		// memory expansion.
+		assert_eq!(default.cost(Opcode::CALLDATACOPY, &stack(&[32, 0, 64]), &memory), 1 + 2 + 3);
+		// Nothing is copied or expanded for an empty length.
+		assert_eq!(default.cost(Opcode::CALLDATACOPY, &stack(&[32, 0, 0]), &memory), 1);
+
+		for opcode in [Opcode::ADD, Opcode::EXP, Opcode::SHA3, Opcode::CALLDATACOPY] {
+			assert_eq!(flat.cost(opcode, &stack(&[0, 64, 64]), &memory), 1);
+		}
+		assert_eq!(StepCosts::default(), StepCosts::DEFAULT);
+	}
+
+	#[test]
+	fn test_weighted_budget() {
+		// PUSH1 1, PUSH1 1, DIV, STOP.
+		let code = vec![0x60, 0x01, 0x60, 0x01, 0x04, 0x00];
+		let machine = || Machine::new(code.clone(), Vec::new(), Vec::new(), 1024, 1024);
+
+		// DIV weighs 2, so it does not fit after the two pushes.
+		let mut weighted = machine();
+		assert_eq!(run(&mut weighted, 3, &StepCosts::DEFAULT), (2, Some(ExitReason::StepLimitReached)));
+		assert_eq!(weighted.position(), &Ok(4));
+		assert_eq!(run(&mut weighted, 3, &StepCosts::DEFAULT), (3, Some(ExitReason::Succeed(ExitSucceed::Stopped))));
+
+		let mut flat = machine();
+		assert_eq!(run(&mut flat, 3, &StepCosts::FLAT), (3, Some(ExitReason::StepLimitReached)));
+		assert_eq!(flat.position(), &Ok(5));
+
+		// The first opcode runs even if it alone exceeds the budget.
+		let mut weighted = machine();
+		assert_eq!(run(&mut weighted, 2, &StepCosts::DEFAULT).0, 2);
+		assert_eq!(run(&mut weighted, 1, &StepCosts::DEFAULT), (2, Some(ExitReason::StepLimitReached)));
+		assert_eq!(weighted.position(), &Ok(5));
+	}
+}
@@ -0,0 +1,283 @@
+use crate::consts::{
+	G_CALLVALUE, G_COPY, G_CREATE, G_EXP, G_INITCODE_WORD, G_LOG, G_LOGDATA, G_LOGTOPIC, G_NEWACCOUNT, G_SHA3,
+	G_MEMORY, G_QUADCOEFFDIV, G_SHA3WORD, G_VERYLOW,
+};
+use evm_core::{ExitError, U256};
+use evm_runtime::Config;
+
+/// Number of 32-byte words needed to cover `len` bytes.
+fn words(len: U256) -> Result<u64, ExitError> {
+	if len > U256::from(u64::MAX) {
+		return Err(ExitError::OutOfGas)
+	}
+
+	let len = len.as_u64();
+	Ok(len.div_ceil(32))
+}
+
+/// Cost of `base` plus `per_word` for every word of `len`.
+fn word_cost(base: u64, per_word: u64, len: U256) -> Result<u64, ExitError> {
+	words(len)?
+		.checked_mul(per_word)
+		.and_then(|cost| cost.checked_add(base))
+		.ok_or(ExitError::OutOfGas)
+}
+
+/// Cost of a memory of `words` 32-byte words, `3 * words + words^2 / 512`.
+pub fn memory_gas(words: u64) -> Result<u64, ExitError> {
+	words
+		.checked_mul(G_MEMORY)
+		.and_then(|linear| {
+			words
+				.checked_mul(words)
+				.map(|square| square / G_QUADCOEFFDIV)
+				.and_then(|quadratic| linear.checked_add(quadratic))
+		})
+		.ok_or(ExitError::OutOfGas)
+}
+
+pub fn sha3_cost(len: U256) -> Result<u64, ExitError> {
+	word_cost(G_SHA3, G_SHA3WORD, len)
+}
+
+pub fn verylowcopy_cost(len: U256) -> Result<u64, ExitError> {
+	word_cost(G_VERYLOW, G_COPY, len)
+}
+
+pub fn extcodecopy_cost(len: U256, is_cold: bool, config: &Config) -> Result<u64, ExitError> {
+	word_cost(address_access_cost(is_cold, config.gas_ext_code, config), G_COPY, len)
+}
+
+/// Cost of accessing an account, which is `regular_value` unless EIP-2929
+/// is enabled.
+pub const fn address_access_cost(is_cold: bool, regular_value: u64, config: &Config) -> u64 {
+	if config.increase_state_access_gas {
+		if is_cold {
+			config.gas_account_access_cold
+		} else {
+			config.gas_storage_read_warm
+		}
+	} else {
+		regular_value
+	}
+}
+
+pub const fn sload_cost(is_cold: bool, config: &Config) -> u64 {
+	if config.increase_state_access_gas {
+		if is_cold {
+			config.gas_sload_cold
+		} else {
+			config.gas_storage_read_warm
+		}
+	} else {
+		config.gas_sload
+	}
+}
+
+pub fn create_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
+	if config.max_initcode_size.is_some() {
+		word_cost(G_CREATE, G_INITCODE_WORD, len)
+	} else {
+		Ok(G_CREATE)
+	}
+}
+
+pub fn create2_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
+	if config.max_initcode_size.is_some() {
+		word_cost(G_CREATE, G_SHA3WORD + G_INITCODE_WORD, len)
+	} else {
+		word_cost(G_CREATE, G_SHA3WORD, len)
+	}
+}
+
+pub fn log_cost(n: u8, len: U256) -> Result<u64, ExitError> {
+	if len > U256::from(u64::MAX) {
+		return Err(ExitError::OutOfGas)
+	}
+
+	len.as_u64()
+		.checked_mul(G_LOGDATA)
+		.and_then(|cost| cost.checked_add(G_LOG))
+		.and_then(|cost| cost.checked_add(G_LOGTOPIC * u64::from(n)))
+		.ok_or(ExitError::OutOfGas)
+}
+
+pub fn exp_cost(power: U256, config: &Config) -> Result<u64, ExitError> {
+	let bytes = (power.bits() as u64).div_ceil(8);
+
+	bytes
+		.checked_mul(config.gas_expbyte)
+		.and_then(|cost| cost.checked_add(G_EXP))
+		.ok_or(ExitError::OutOfGas)
+}
+
+pub fn sstore_cost(
+	original: U256,
+	current: U256,
+	new: U256,
+	gas: u64,
+	is_cold: bool,
+	config: &Config,
+) -> Result<u64, ExitError> {
+	let gas_cost = if config.sstore_gas_metering {
+		if config.sstore_revert_under_stipend && gas <= config.call_stipend {
+			return Err(ExitError::OutOfGas)
+		}
+
+		if new == current {
+			config.gas_sload
+		} else if original == current {
+			if original.is_zero() {
+				config.gas_sstore_set
+			} else {
+				config.gas_sstore_reset
+			}
+		} else {
+			config.gas_sload
+		}
+	} else if current.is_zero() && !new.is_zero() {
+		config.gas_sstore_set
+	} else {
+		config.gas_sstore_reset
+	};
+
+	Ok(if is_cold {
+		gas_cost + config.gas_sload_cold
+	} else {
+		gas_cost
+	})
+}
+
+#[allow(clippy::cast_possible_wrap)]
+pub fn sstore_refund(original: U256, current: U256, new: U256, config: &Config) -> i64 {
+	if !config.sstore_gas_metering {
+		return if !current.is_zero() && new.is_zero() {
+			config.refund_sstore_clears
+		} else {
+			0
+		}
+	}
+
+	if current == new {
+		return 0
+	}
+
+	if original == current && new.is_zero() {
+		return config.refund_sstore_clears
+	}
+
+	let mut refund = 0;
+
+	if !original.is_zero() {
+		if current.is_zero() {
+			refund -= config.refund_sstore_clears;
+		} else if new.is_zero() {
+			refund += config.refund_sstore_clears;
+		}
+	}
+
+	if original == new {
+		if original.is_zero() {
+			refund += (config.gas_sstore_set - config.gas_sload) as i64;
+		} else {
+			refund += (config.gas_sstore_reset - config.gas_sload) as i64;
+		}
+	}
+
+	refund
+}
+
+pub const fn suicide_cost(value: U256, is_cold: bool, target_exists: bool, config: &Config) -> u64 {
+	let eip161 = !config.empty_considered_exists;
+	let should_charge_topup = if eip161 {
+		!value.is_zero() && !target_exists
+	} else {
+		!target_exists
+	};
+
+	let mut gas = config.gas_suicide;
+	if should_charge_topup {
+		gas += config.gas_suicide_new_account;
+	}
+	if config.increase_state_access_gas && is_cold {
+		gas += config.gas_account_access_cold;
+	}
+
+	gas
+}
+
+#[allow(clippy::fn_params_excessive_bools)]
+pub const fn call_cost(
+	value: U256,
+	is_cold: bool,
+	is_call_or_callcode: bool,
+	is_call_or_staticcall: bool,
+	new_account: bool,
+	config: &Config,
+) -> u64 {
+	let transfers_value = !value.is_zero();
+
+	address_access_cost(is_cold, config.gas_call, config)
+		+ xfer_cost(is_call_or_callcode, transfers_value)
+		+ new_cost(is_call_or_staticcall, new_account, transfers_value, config)
+}
+
+const fn xfer_cost(is_call_or_callcode: bool, transfers_value: bool) -> u64 {
+	if is_call_or_callcode && transfers_value {
+		G_CALLVALUE
+	} else {
+		0
+	}
+}
+
+const fn new_cost(
+	is_call_or_staticcall: bool,
+	new_account: bool,
+	transfers_value: bool,
+	config: &Config,
+) -> u64 {
+	let eip161 = !config.empty_considered_exists;
+	if is_call_or_staticcall {
+		if eip161 {
+			if transfers_value && new_account {
+				G_NEWACCOUNT
+			} else {
+				0
+			}
+		} else if new_account {
+			G_NEWACCOUNT
+		} else {
+			0
+		}
+	} else {
+		0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{memory_gas, sstore_refund};
+	use evm_core::U256;
+	use evm_runtime::Config;
+
+	#[test]
+	fn test_memory_gas() {
+		assert_eq!(memory_gas(0), Ok(0));
+		assert_eq!(memory_gas(1), Ok(3));
+		assert_eq!(memory_gas(32), Ok(98));
+		assert_eq!(memory_gas(1024), Ok(5120));
+		assert!(memory_gas(u64::MAX).is_err());
+	}
+
+	#[test]
+	fn test_sstore_refund() {
+		let config = Config::istanbul();
+		let (zero, one, two) = (U256::zero(), U256::one(), U256::from(2));
+
+		assert_eq!(sstore_refund(one, one, zero, &config), 15000);
+		assert_eq!(sstore_refund(one, two, zero, &config), 15000);
+		assert_eq!(sstore_refund(one, zero, one, &config), -15000 + 4200);
+		assert_eq!(sstore_refund(zero, one, zero, &config), 19200);
+		assert_eq!(sstore_refund(one, one, one, &config), 0);
+	}
+}
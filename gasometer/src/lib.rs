@@ -0,0 +1,1107 @@
+//! Gas accounting layer for EVM.
+//!
+//! The gasometer prices every opcode according to the active `Config`. It
+//! is usually driven through `Gasometer::record_opcode` from
+//! `Handler::pre_validate`, which `Runtime::run` calls before executing each
+//! opcode, but it can also be used on its own:
+//!
+//! ```
+//! use evm_core::{Opcode, U256};
+//! use evm_gasometer::{static_opcode_cost, GasCost, Gasometer, MemoryCost};
+//! use evm_runtime::Config;
+//!
+//! let config = Config::cancun();
+//! let mut gasometer = Gasometer::new(1000, &config);
+//!
+//! // ADD has a fixed price.
+//! gasometer.record_cost(static_opcode_cost(Opcode::ADD).unwrap()).unwrap();
+//! // EXP with a two-byte exponent, priced from its operands.
+//! gasometer.record_dynamic_cost(GasCost::Exp { power: U256::from(0x100) }, None).unwrap();
+//! // MSTORE at offset 0, expanding memory to one word.
+//! let memory = MemoryCost { offset: U256::zero(), len: U256::from(32) };
+//! gasometer.record_dynamic_cost(GasCost::VeryLow, Some(memory)).unwrap();
+//!
+//! assert_eq!(gasometer.total_used_gas(), 3 + 110 + 6);
+//! ```
+
+#![deny(warnings)]
+#![forbid(unsafe_code, unused_variables)]
+#![deny(clippy::all, clippy::pedantic, clippy::nursery)]
+#![allow(
+	clippy::module_name_repetitions,
+	clippy::missing_errors_doc,
+	clippy::missing_panics_doc
+)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![forbid(unused_imports)]
+
+extern crate alloc;
+
+mod consts;
+mod costs;
+
+use alloc::vec::Vec;
+use core::{cmp::{max, min}, convert::TryFrom};
+use evm_core::{ExitError, Opcode, Stack, H160, H256, U256};
+use evm_runtime::{Config, Handler};
+use crate::consts::{
+	G_BASE, G_BLOCKHASH, G_CODEDEPOSIT, G_HIGH, G_INITCODE_WORD, G_JUMPDEST, G_LOW, G_MID, G_VERYLOW,
+	G_WARM_STORAGE_READ, G_ZERO,
+};
+
+/// EVM gasometer.
+#[derive(Clone, Debug)]
+pub struct Gasometer<'config> {
+	gas_limit: u64,
+	used_gas: u64,
+	peak_used_gas: u64,
+	memory_gas: u64,
+	refunded_gas: i64,
+	floor_gas: u64,
+	status: Result<(), ExitError>,
+	config: &'config Config,
+}
+
+impl<'config> Gasometer<'config> {
+	/// Create a new gasometer with given gas limit and config.
+	#[must_use]
+	pub const fn new(gas_limit: u64, config: &'config Config) -> Self {
+		Self {
+			gas_limit,
+			used_gas: 0,
+			peak_used_gas: 0,
+			memory_gas: 0,
+			refunded_gas: 0,
+			floor_gas: 0,
+			status: Ok(()),
+			config,
+		}
+	}
+
+	/// Reference of the config.
+	#[must_use]
+	pub const fn config(&self) -> &'config Config {
+		self.config
+	}
+
+	/// Gas limit.
+	#[must_use]
+	pub const fn gas_limit(&self) -> u64 {
+		self.gas_limit
+	}
+
+	/// Remaining gas.
+	#[must_use]
+	pub const fn gas(&self) -> u64 {
+		match self.status {
+			Ok(()) => self.gas_limit - self.used_gas,
+			Err(_) => 0,
+		}
+	}
+
+	/// Total used gas. In estimate mode, this is the most gas the frame
+	/// needed at any point, see `record_call_reserve`.
+	#[must_use]
+	pub const fn total_used_gas(&self) -> u64 {
+		match self.status {
+			Ok(()) if self.peak_used_gas > self.used_gas => self.peak_used_gas,
+			Ok(()) => self.used_gas,
+			Err(_) => self.gas_limit,
+		}
+	}
+
+	/// Refund counter. It can be negative within a call frame, as restoring
+	/// a cleared slot takes back an earlier refund.
+	#[must_use]
+	pub const fn refunded_gas(&self) -> i64 {
+		self.refunded_gas
+	}
+
+	/// Gas used after applying the refund counter at the end of the
+	/// transaction. The refund is capped at `total_used_gas /
+	/// max_refund_quotient` (EIP-3529), and the result is never below the
+	/// calldata floor of the transaction (EIP-7623). In estimate mode the
+	/// refund is ignored, as the gas limit has to cover the gas used before
+	/// it is applied.
+	#[must_use]
+	pub fn effective_used_gas(&self) -> u64 {
+		let total_used_gas = self.total_used_gas();
+		let refund = u64::try_from(self.refunded_gas).unwrap_or(0);
+		let used_gas = if self.config.estimate {
+			total_used_gas
+		} else {
+			total_used_gas - min(total_used_gas / self.config.max_refund_quotient, refund)
+		};
+
+		self.floored(used_gas)
+	}
+
+	/// Gas used by a reverted transaction, which gets no refund: the total
+	/// used gas, never below the calldata floor (EIP-7623).
+	#[must_use]
+	pub fn reverted_used_gas(&self) -> u64 {
+		self.floored(self.total_used_gas())
+	}
+
+	/// Raise `used_gas` to the calldata floor of the transaction.
+	fn floored(&self, used_gas: u64) -> u64 {
+		max(used_gas, self.floor_gas)
+	}
+
+	/// Explicitly fail the gasometer with out of gas. Any further recording
+	/// fails, and all gas is considered used.
+	pub const fn fail(&mut self) -> ExitError {
+		self.status = Err(ExitError::OutOfGas);
+		ExitError::OutOfGas
+	}
+
+	/// Record an explicit cost.
+	pub fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+		self.status?;
+
+		match self.used_gas.checked_add(cost) {
+			Some(used_gas) if used_gas <= self.gas_limit => {
+				self.used_gas = used_gas;
+				Ok(())
+			},
+			_ => Err(self.fail()),
+		}
+	}
+
+	/// Record the intrinsic cost of a transaction. Fails if the gas limit
+	/// does not cover it or the calldata floor, or exceeds the transaction
+	/// gas limit cap.
+	pub fn record_transaction(&mut self, cost: TransactionCost) -> Result<(), ExitError> {
+		self.status?;
+
+		let floor_gas = cost.floor_cost(self.config);
+		let over_cap = self.config.max_transaction_gas_limit.is_some_and(|cap| self.gas_limit > cap);
+		if self.gas_limit < floor_gas || over_cap {
+			return Err(self.fail())
+		}
+
+		self.record_cost(cost.cost(self.config)?)?;
+		self.floor_gas = floor_gas;
+		Ok(())
+	}
+
+	/// Record a refund, or take one back if `refund` is negative.
+	pub fn record_refund(&mut self, refund: i64) -> Result<(), ExitError> {
+		self.status?;
+
+		self.refunded_gas += refund;
+		Ok(())
+	}
+
+	/// Account, in estimate mode, for the gas that EIP-150 withholds from a
+	/// sub-call or create, once it has returned with `child` as gasometer.
+	/// For the sub-call to receive the gas it needed, this frame must have
+	/// held about 64/63 of it when making the call. `total_used_gas` reports
+	/// that peak if it exceeds the gas eventually used, while the gas left to
+	/// the frame is unchanged.
+	pub fn record_call_reserve(&mut self, child: &Self) -> Result<(), ExitError> {
+		self.status?;
+
+		if !self.config.estimate || !self.config.call_l64_after_gas {
+			return Ok(())
+		}
+
+		// `needed + reserve` is the smallest `g` with `g - g / 64 >= needed`.
+		let needed = child.total_used_gas();
+		let reserve = needed.saturating_sub(1) / 63;
+		let peak = self.used_gas.saturating_add(needed - child.used_gas).saturating_add(reserve);
+		self.peak_used_gas = max(self.peak_used_gas, min(peak, self.gas_limit));
+		Ok(())
+	}
+
+	/// Record the cost of depositing `len` bytes of code at the end of a
+	/// create.
+	pub fn record_deposit(&mut self, len: usize) -> Result<(), ExitError> {
+		match u64::try_from(len).ok().and_then(|len| len.checked_mul(G_CODEDEPOSIT)) {
+			Some(cost) => self.record_cost(cost),
+			None => Err(self.fail()),
+		}
+	}
+
+	/// Give back gas to this frame, typically what a sub-call or create left
+	/// unused.
+	pub fn record_stipend(&mut self, stipend: u64) -> Result<(), ExitError> {
+		self.status?;
+
+		self.used_gas = self.used_gas.saturating_sub(stipend);
+		Ok(())
+	}
+
+	/// Record the cost of an opcode whose price depends on its operands or
+	/// on the state, as computed by `dynamic_opcode_cost`, together with the
+	/// cost of expanding memory to cover `memory`.
+	pub fn record_dynamic_cost(
+		&mut self,
+		cost: GasCost,
+		memory: Option<MemoryCost>,
+	) -> Result<(), ExitError> {
+		self.status?;
+
+		let memory_gas = match memory.map_or(Ok(self.memory_gas), MemoryCost::cost) {
+			Ok(memory_gas) => memory_gas.max(self.memory_gas),
+			Err(_) => return Err(self.fail()),
+		};
+		let Ok(gas_cost) = cost.cost(self.gas(), self.config) else {
+			return Err(self.fail())
+		};
+		let Some(total) = gas_cost.checked_add(memory_gas - self.memory_gas) else {
+			return Err(self.fail())
+		};
+		let refund = match cost {
+			GasCost::SStore { original, current, new, .. } =>
+				costs::sstore_refund(original, current, new, self.config),
+			_ => 0,
+		};
+
+		self.record_cost(total)?;
+		self.memory_gas = memory_gas;
+		self.refunded_gas += refund;
+		Ok(())
+	}
+
+	/// Record the cost of `opcode` run by `address` with the given stack,
+	/// querying `handler` for the state its price depends on. Returns the
+	/// account or slot the opcode accesses, which the caller marks warm.
+	pub fn record_opcode<H: Handler>(
+		&mut self,
+		address: H160,
+		opcode: Opcode,
+		stack: &Stack,
+		handler: &H,
+	) -> Result<StorageTarget, ExitError> {
+		if let Some(cost) = static_opcode_cost(opcode) {
+			self.record_cost(cost)?;
+			return Ok(StorageTarget::None)
+		}
+
+		let (cost, target, memory) = dynamic_opcode_cost(address, opcode, stack, self.config, handler)?;
+		self.record_dynamic_cost(cost, memory)?;
+		Ok(target)
+	}
+
+}
+
+/// Intrinsic cost of a transaction, paid before any of its code runs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TransactionCost {
+	/// Whether the transaction creates a contract.
+	pub is_create: bool,
+	/// Number of zero bytes of calldata, or init code.
+	pub zero_data_len: usize,
+	/// Number of non-zero bytes of calldata, or init code.
+	pub non_zero_data_len: usize,
+	/// Number of addresses in the access list.
+	pub access_list_address_len: usize,
+	/// Number of storage keys in the access list.
+	pub access_list_storage_len: usize,
+	/// Number of authorizations (EIP-7702).
+	pub authorization_list_len: usize,
+	/// Length of the init code, zero for calls.
+	pub initcode_len: usize,
+}
+
+impl TransactionCost {
+	/// Intrinsic gas of the transaction. Fails with `CreateContractLimit` if
+	/// the init code exceeds `max_initcode_size`.
+	pub fn cost(&self, config: &Config) -> Result<u64, ExitError> {
+		let base = if self.is_create {
+			config.gas_transaction_create
+		} else {
+			config.gas_transaction_call
+		};
+
+		let initcode_cost = match config.max_initcode_size {
+			Some(max_initcode_size) if self.initcode_len > max_initcode_size =>
+				return Err(ExitError::CreateContractLimit),
+			Some(_) => (self.initcode_len as u64).div_ceil(32) * G_INITCODE_WORD,
+			None => 0,
+		};
+
+		[
+			(self.zero_data_len, config.gas_transaction_zero_data),
+			(self.non_zero_data_len, config.gas_transaction_non_zero_data),
+			(self.access_list_address_len, config.gas_access_list_address),
+			(self.access_list_storage_len, config.gas_access_list_storage_key),
+			(self.authorization_list_len, config.gas_per_empty_account_cost),
+		]
+			.iter()
+			.try_fold(base + initcode_cost, |cost, (len, gas)| {
+				(*len as u64).checked_mul(*gas).and_then(|gas| cost.checked_add(gas))
+			})
+			.ok_or(ExitError::OutOfGas)
+	}
+
+	/// Minimum gas used by the transaction, priced per calldata token where
+	/// a non-zero byte counts as four tokens (EIP-7623).
+	#[must_use]
+	pub const fn floor_cost(&self, config: &Config) -> u64 {
+		if config.total_cost_floor_per_token == 0 {
+			return 0
+		}
+
+		let tokens = self.zero_data_len as u64 + self.non_zero_data_len as u64 * 4;
+
+		tokens
+			.saturating_mul(config.total_cost_floor_per_token)
+			.saturating_add(config.gas_transaction_call)
+	}
+
+	/// Smallest gas limit the transaction can be sent with.
+	pub fn min_gas_limit(&self, config: &Config) -> Result<u64, ExitError> {
+		Ok(max(self.cost(config)?, self.floor_cost(config)))
+	}
+}
+
+/// Intrinsic cost of a call transaction with the given calldata, access
+/// list (EIP-2930) and number of authorizations (EIP-7702).
+#[must_use]
+#[allow(clippy::naive_bytecount)]
+pub fn call_transaction_cost(
+	data: &[u8],
+	access_list: &[(H160, Vec<U256>)],
+	authorization_list_len: usize,
+) -> TransactionCost {
+	let zero_data_len = data.iter().filter(|v| **v == 0).count();
+	let (access_list_address_len, access_list_storage_len) = count_access_list(access_list);
+
+	TransactionCost {
+		is_create: false,
+		zero_data_len,
+		non_zero_data_len: data.len() - zero_data_len,
+		access_list_address_len,
+		access_list_storage_len,
+		authorization_list_len,
+		initcode_len: 0,
+	}
+}
+
+/// Intrinsic cost of a create transaction with the given init code and
+/// access list (EIP-2930).
+#[must_use]
+pub fn create_transaction_cost(data: &[u8], access_list: &[(H160, Vec<U256>)]) -> TransactionCost {
+	TransactionCost {
+		is_create: true,
+		initcode_len: data.len(),
+		..call_transaction_cost(data, access_list, 0)
+	}
+}
+
+fn count_access_list(access_list: &[(H160, Vec<U256>)]) -> (usize, usize) {
+	let access_list_address_len = access_list.len();
+	let access_list_storage_len = access_list.iter().map(|(_, keys)| keys.len()).sum();
+
+	(access_list_address_len, access_list_storage_len)
+}
+
+/// Memory region touched by an opcode, used to price memory expansion.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryCost {
+	/// Affected memory offset.
+	pub offset: U256,
+	/// Affected length.
+	pub len: U256,
+}
+
+impl MemoryCost {
+	/// Memory region covering both `self` and `other`.
+	#[must_use]
+	pub fn join(self, other: Self) -> Self {
+		if self.len.is_zero() {
+			return other
+		}
+
+		if other.len.is_zero() {
+			return self
+		}
+
+		let self_end = self.offset.saturating_add(self.len);
+		let other_end = other.offset.saturating_add(other.len);
+
+		if self_end >= other_end {
+			self
+		} else {
+			other
+		}
+	}
+
+	/// Gas cost of a memory large enough to cover this region. An empty
+	/// region does not expand memory.
+	pub fn cost(self) -> Result<u64, ExitError> {
+		if self.len.is_zero() {
+			return Ok(0)
+		}
+
+		let end = self.offset.checked_add(self.len).ok_or(ExitError::OutOfGas)?;
+		if end > U256::from(u64::MAX) {
+			return Err(ExitError::OutOfGas)
+		}
+
+		costs::memory_gas(end.as_u64().div_ceil(32))
+	}
+}
+
+/// Gas cost of an opcode whose price cannot be determined from the opcode
+/// alone.
+#[derive(Debug, Clone, Copy)]
+pub enum GasCost {
+	/// Zero gas cost.
+	Zero,
+	/// Base gas cost.
+	Base,
+	/// Very low gas cost.
+	VeryLow,
+	/// Low gas cost.
+	Low,
+	/// Opcode unknown to the gasometer. It is left to the interpreter to
+	/// reject it.
+	Invalid,
+
+	/// Gas cost for `EXTCODESIZE`.
+	ExtCodeSize {
+		/// True if address has not been previously accessed in this transaction.
+		target_is_cold: bool,
+	},
+	/// Gas cost for `BALANCE`.
+	Balance {
+		/// True if address has not been previously accessed in this transaction.
+		target_is_cold: bool,
+	},
+	/// Gas cost for `EXTCODEHASH`.
+	ExtCodeHash {
+		/// True if address has not been previously accessed in this transaction.
+		target_is_cold: bool,
+	},
+	/// Gas cost for `SLOAD`.
+	SLoad {
+		/// True if the slot has not been previously accessed in this transaction.
+		target_is_cold: bool,
+	},
+
+	/// Gas cost for `CALL`.
+	Call {
+		/// Call value.
+		value: U256,
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Whether the target exists.
+		target_exists: bool,
+	},
+	/// Gas cost for `CALLCODE`.
+	CallCode {
+		/// Call value.
+		value: U256,
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Whether the target exists.
+		target_exists: bool,
+	},
+	/// Gas cost for `DELEGATECALL`.
+	DelegateCall {
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Whether the target exists.
+		target_exists: bool,
+	},
+	/// Gas cost for `STATICCALL`.
+	StaticCall {
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Whether the target exists.
+		target_exists: bool,
+	},
+	/// Gas cost for `SUICIDE`.
+	Suicide {
+		/// Value to be transferred.
+		value: U256,
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Whether the target exists.
+		target_exists: bool,
+	},
+	/// Gas cost for `SSTORE`.
+	SStore {
+		/// Value at the beginning of the transaction.
+		original: U256,
+		/// Current value.
+		current: U256,
+		/// New value.
+		new: U256,
+		/// True if the slot has not been previously accessed in this transaction.
+		target_is_cold: bool,
+	},
+	/// Gas cost for `SHA3`.
+	Sha3 {
+		/// Length of the data.
+		len: U256,
+	},
+	/// Gas cost for `LOG`.
+	Log {
+		/// Topic length.
+		n: u8,
+		/// Data length.
+		len: U256,
+	},
+	/// Gas cost for `EXTCODECOPY`.
+	ExtCodeCopy {
+		/// True if target has not been previously accessed in this transaction.
+		target_is_cold: bool,
+		/// Length.
+		len: U256,
+	},
+	/// Gas cost for some copy opcodes that is documented as `VERYLOW`.
+	VeryLowCopy {
+		/// Length.
+		len: U256,
+	},
+	/// Gas cost for `EXP`.
+	Exp {
+		/// Power of `EXP`.
+		power: U256,
+	},
+	/// Gas cost for `CREATE`.
+	Create {
+		/// Length.
+		len: U256,
+	},
+	/// Gas cost for `CREATE2`.
+	Create2 {
+		/// Length.
+		len: U256,
+	},
+}
+
+impl GasCost {
+	/// Price of the opcode under the given config, with `gas` remaining
+	/// before it is charged.
+	pub fn cost(&self, gas: u64, config: &Config) -> Result<u64, ExitError> {
+		Ok(match *self {
+			Self::Zero | Self::Invalid => G_ZERO,
+			Self::Base => G_BASE,
+			Self::VeryLow => G_VERYLOW,
+			Self::Low => G_LOW,
+
+			Self::ExtCodeSize { target_is_cold } =>
+				costs::address_access_cost(target_is_cold, config.gas_ext_code, config),
+			Self::Balance { target_is_cold } =>
+				costs::address_access_cost(target_is_cold, config.gas_balance, config),
+			Self::ExtCodeHash { target_is_cold } =>
+				costs::address_access_cost(target_is_cold, config.gas_ext_code_hash, config),
+			Self::SLoad { target_is_cold } => costs::sload_cost(target_is_cold, config),
+
+			Self::Call { value, target_is_cold, target_exists } =>
+				costs::call_cost(value, target_is_cold, true, true, !target_exists, config),
+			Self::CallCode { value, target_is_cold, target_exists } =>
+				costs::call_cost(value, target_is_cold, true, false, !target_exists, config),
+			Self::DelegateCall { target_is_cold, target_exists } =>
+				costs::call_cost(U256::zero(), target_is_cold, false, false, !target_exists, config),
+			Self::StaticCall { target_is_cold, target_exists } =>
+				costs::call_cost(U256::zero(), target_is_cold, false, true, !target_exists, config),
+			Self::Suicide { value, target_is_cold, target_exists } =>
+				costs::suicide_cost(value, target_is_cold, target_exists, config),
+			Self::SStore { original, current, new, target_is_cold } =>
+				costs::sstore_cost(original, current, new, gas, target_is_cold, config)?,
+
+			Self::Sha3 { len } => costs::sha3_cost(len)?,
+			Self::Log { n, len } => costs::log_cost(n, len)?,
+			Self::ExtCodeCopy { target_is_cold, len } =>
+				costs::extcodecopy_cost(len, target_is_cold, config)?,
+			Self::VeryLowCopy { len } => costs::verylowcopy_cost(len)?,
+			Self::Exp { power } => costs::exp_cost(power, config)?,
+			Self::Create { len } => costs::create_cost(len, config)?,
+			Self::Create2 { len } => costs::create2_cost(len, config)?,
+		})
+	}
+}
+
+/// Static cost of an opcode, or `None` if its price depends on the config,
+/// the state or its operands.
+#[must_use]
+pub fn static_opcode_cost(opcode: Opcode) -> Option<u64> {
+	static TABLE: [Option<u64>; 256] = {
+		let mut table = [None; 256];
+
+		table[Opcode::STOP.as_usize()] = Some(G_ZERO);
+		table[Opcode::CALLDATASIZE.as_usize()] = Some(G_BASE);
+		table[Opcode::CODESIZE.as_usize()] = Some(G_BASE);
+		table[Opcode::POP.as_usize()] = Some(G_BASE);
+		table[Opcode::PC.as_usize()] = Some(G_BASE);
+		table[Opcode::MSIZE.as_usize()] = Some(G_BASE);
+		table[Opcode::PUSH0.as_usize()] = Some(G_BASE);
+
+		table[Opcode::ADDRESS.as_usize()] = Some(G_BASE);
+		table[Opcode::ORIGIN.as_usize()] = Some(G_BASE);
+		table[Opcode::CALLER.as_usize()] = Some(G_BASE);
+		table[Opcode::CALLVALUE.as_usize()] = Some(G_BASE);
+		table[Opcode::COINBASE.as_usize()] = Some(G_BASE);
+		table[Opcode::TIMESTAMP.as_usize()] = Some(G_BASE);
+		table[Opcode::NUMBER.as_usize()] = Some(G_BASE);
+		table[Opcode::DIFFICULTY.as_usize()] = Some(G_BASE);
+		table[Opcode::GASLIMIT.as_usize()] = Some(G_BASE);
+		table[Opcode::GASPRICE.as_usize()] = Some(G_BASE);
+		table[Opcode::GAS.as_usize()] = Some(G_BASE);
+		table[Opcode::CHAINID.as_usize()] = Some(G_BASE);
+		table[Opcode::BASEFEE.as_usize()] = Some(G_BASE);
+		table[Opcode::RETURNDATASIZE.as_usize()] = Some(G_BASE);
+
+		table[Opcode::ADD.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SUB.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::NOT.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::LT.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::GT.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SLT.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SGT.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::EQ.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::ISZERO.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::AND.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::OR.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::XOR.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::BYTE.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SHL.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SHR.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::SAR.as_usize()] = Some(G_VERYLOW);
+		table[Opcode::CALLDATALOAD.as_usize()] = Some(G_VERYLOW);
+
+		let mut i = Opcode::PUSH1.as_usize();
+		while i <= Opcode::SWAP16.as_usize() {
+			table[i] = Some(G_VERYLOW);
+			i += 1;
+		}
+
+		table[Opcode::MUL.as_usize()] = Some(G_LOW);
+		table[Opcode::DIV.as_usize()] = Some(G_LOW);
+		table[Opcode::SDIV.as_usize()] = Some(G_LOW);
+		table[Opcode::MOD.as_usize()] = Some(G_LOW);
+		table[Opcode::SMOD.as_usize()] = Some(G_LOW);
+		table[Opcode::SIGNEXTEND.as_usize()] = Some(G_LOW);
+		table[Opcode::SELFBALANCE.as_usize()] = Some(G_LOW);
+
+		table[Opcode::ADDMOD.as_usize()] = Some(G_MID);
+		table[Opcode::MULMOD.as_usize()] = Some(G_MID);
+		table[Opcode::JUMP.as_usize()] = Some(G_MID);
+
+		table[Opcode::JUMPI.as_usize()] = Some(G_HIGH);
+		table[Opcode::JUMPDEST.as_usize()] = Some(G_JUMPDEST);
+		table[Opcode::BLOCKHASH.as_usize()] = Some(G_BLOCKHASH);
+
+		table[Opcode::TLOAD.as_usize()] = Some(G_WARM_STORAGE_READ);
+		table[Opcode::TSTORE.as_usize()] = Some(G_WARM_STORAGE_READ);
+
+		table
+	};
+
+	TABLE[opcode.as_usize()]
+}
+
+fn peek_address(stack: &Stack, no_from_top: usize) -> Result<H160, ExitError> {
+	Ok(H256::from(stack.peek(no_from_top)?).into())
+}
+
+/// Account or storage slot accessed by an opcode, which becomes warm once
+/// the opcode has been paid for (EIP-2929).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StorageTarget {
+	/// No state is accessed.
+	None,
+	/// An account is accessed.
+	Address(H160),
+	/// A storage slot of an account is accessed.
+	Slot(H160, U256),
+}
+
+/// Cost of an opcode that is not covered by `static_opcode_cost`, the state
+/// it accesses, and the memory region it touches. Stack operands are only
+/// peeked, so the opcode itself still sees them.
+#[allow(clippy::too_many_lines)]
+pub fn dynamic_opcode_cost<H: Handler>(
+	address: H160,
+	opcode: Opcode,
+	stack: &Stack,
+	config: &Config,
+	handler: &H,
+) -> Result<(GasCost, StorageTarget, Option<MemoryCost>), ExitError> {
+	let target_exists = |target: H160| {
+		if config.empty_considered_exists {
+			handler.exists(target)
+		} else {
+			!(handler.balance(target).is_zero()
+				&& handler.nonce(target).is_zero()
+				&& handler.code_size(target).is_zero())
+		}
+	};
+	let is_cold = |target: H160, index: Option<U256>| {
+		config.increase_state_access_gas && handler.is_cold(target, index)
+	};
+
+	let mut storage_target = StorageTarget::None;
+	let gas_cost = match opcode {
+		Opcode::EXTCODESIZE | Opcode::BALANCE | Opcode::EXTCODEHASH | Opcode::EXTCODECOPY => {
+			let target = peek_address(stack, 0)?;
+			let target_is_cold = is_cold(target, None);
+			storage_target = StorageTarget::Address(target);
+
+			match opcode {
+				Opcode::EXTCODESIZE => GasCost::ExtCodeSize { target_is_cold },
+				Opcode::BALANCE => GasCost::Balance { target_is_cold },
+				Opcode::EXTCODEHASH => GasCost::ExtCodeHash { target_is_cold },
+				_ => GasCost::ExtCodeCopy { target_is_cold, len: stack.peek(3)? },
+			}
+		},
+		Opcode::SLOAD => {
+			let index = stack.peek(0)?;
+			storage_target = StorageTarget::Slot(address, index);
+
+			GasCost::SLoad { target_is_cold: is_cold(address, Some(index)) }
+		},
+		Opcode::SSTORE => {
+			let index = stack.peek(0)?;
+			storage_target = StorageTarget::Slot(address, index);
+
+			GasCost::SStore {
+				original: handler.original_storage(address, index),
+				current: handler.storage(address, index),
+				new: stack.peek(1)?,
+				target_is_cold: is_cold(address, Some(index)),
+			}
+		},
+
+		Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL => {
+			let target = peek_address(stack, 1)?;
+			let target_is_cold = is_cold(target, None);
+			let target_exists = target_exists(target);
+			storage_target = StorageTarget::Address(target);
+
+			match opcode {
+				Opcode::CALL => GasCost::Call { value: stack.peek(2)?, target_is_cold, target_exists },
+				Opcode::CALLCODE => GasCost::CallCode { value: stack.peek(2)?, target_is_cold, target_exists },
+				Opcode::DELEGATECALL => GasCost::DelegateCall { target_is_cold, target_exists },
+				_ => GasCost::StaticCall { target_is_cold, target_exists },
+			}
+		},
+		Opcode::SUICIDE => {
+			let target = peek_address(stack, 0)?;
+			storage_target = StorageTarget::Address(target);
+
+			GasCost::Suicide {
+				value: handler.balance(address),
+				target_is_cold: is_cold(target, None),
+				target_exists: target_exists(target),
+			}
+		},
+
+		Opcode::SHA3 => GasCost::Sha3 { len: stack.peek(1)? },
+		Opcode::LOG0 => GasCost::Log { n: 0, len: stack.peek(1)? },
+		Opcode::LOG1 => GasCost::Log { n: 1, len: stack.peek(1)? },
+		Opcode::LOG2 => GasCost::Log { n: 2, len: stack.peek(1)? },
+		Opcode::LOG3 => GasCost::Log { n: 3, len: stack.peek(1)? },
+		Opcode::LOG4 => GasCost::Log { n: 4, len: stack.peek(1)? },
+		Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY | Opcode::MCOPY =>
+			GasCost::VeryLowCopy { len: stack.peek(2)? },
+		Opcode::EXP => GasCost::Exp { power: stack.peek(1)? },
+		Opcode::CREATE => GasCost::Create { len: stack.peek(2)? },
+		Opcode::CREATE2 => GasCost::Create2 { len: stack.peek(2)? },
+
+		Opcode::MLOAD | Opcode::MSTORE | Opcode::MSTORE8 => GasCost::VeryLow,
+		Opcode::RETURN | Opcode::REVERT => GasCost::Zero,
+
+		_ => GasCost::Invalid,
+	};
+
+	Ok((gas_cost, storage_target, memory_cost(opcode, stack)?))
+}
+
+/// Memory region touched by an opcode, if any.
+fn memory_cost(opcode: Opcode, stack: &Stack) -> Result<Option<MemoryCost>, ExitError> {
+	Ok(match opcode {
+		Opcode::SHA3 | Opcode::RETURN | Opcode::REVERT
+		| Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => Some(MemoryCost {
+			offset: stack.peek(0)?,
+			len: stack.peek(1)?,
+		}),
+		Opcode::CODECOPY | Opcode::CALLDATACOPY | Opcode::RETURNDATACOPY => Some(MemoryCost {
+			offset: stack.peek(0)?,
+			len: stack.peek(2)?,
+		}),
+		Opcode::MCOPY => Some(MemoryCost {
+			offset: stack.peek(0)?.max(stack.peek(1)?),
+			len: stack.peek(2)?,
+		}),
+		Opcode::EXTCODECOPY => Some(MemoryCost {
+			offset: stack.peek(1)?,
+			len: stack.peek(3)?,
+		}),
+		Opcode::MLOAD | Opcode::MSTORE => Some(MemoryCost {
+			offset: stack.peek(0)?,
+			len: U256::from(32),
+		}),
+		Opcode::MSTORE8 => Some(MemoryCost {
+			offset: stack.peek(0)?,
+			len: U256::one(),
+		}),
+		Opcode::CREATE | Opcode::CREATE2 => Some(MemoryCost {
+			offset: stack.peek(1)?,
+			len: stack.peek(2)?,
+		}),
+		Opcode::CALL | Opcode::CALLCODE => Some(
+			MemoryCost {
+				offset: stack.peek(3)?,
+				len: stack.peek(4)?,
+			}
+			.join(MemoryCost {
+				offset: stack.peek(5)?,
+				len: stack.peek(6)?,
+			}),
+		),
+		Opcode::DELEGATECALL | Opcode::STATICCALL => Some(
+			MemoryCost {
+				offset: stack.peek(2)?,
+				len: stack.peek(3)?,
+			}
+			.join(MemoryCost {
+				offset: stack.peek(4)?,
+				len: stack.peek(5)?,
+			}),
+		),
+		_ => None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{collections::BTreeMap, vec::Vec};
+	use core::convert::Infallible;
+	use super::{call_transaction_cost, create_transaction_cost, Gasometer, StorageTarget};
+	use evm_core::{
+		Capture, Context, CreateScheme, ExitError, ExitFatal, ExitReason, ExitSucceed, Machine, Opcode, Stack, Transfer,
+	};
+	use evm_core::{H160, H256, U256};
+	use evm_runtime::{Config, Handler};
+
+	/// State the opcode prices depend on: accounts that exist, current and
+	/// original storage of the running contract, and what is warm.
+	#[derive(Default)]
+	struct State {
+		existing: Vec<H160>,
+		storage: BTreeMap<U256, U256>,
+		original_storage: BTreeMap<U256, U256>,
+		warm: Vec<(H160, Option<U256>)>,
+	}
+
+	impl Handler for State {
+		type CreateInterrupt = Infallible;
+		type CreateFeedback = Infallible;
+		type CallInterrupt = Infallible;
+		type CallFeedback = Infallible;
+
+		fn keccak256_h256(&self, _data: &[u8]) -> H256 { H256::zero() }
+		fn nonce(&self, _address: H160) -> U256 { U256::zero() }
+		fn exists(&self, address: H160) -> bool { self.existing.contains(&address) }
+		fn balance(&self, address: H160) -> U256 { U256::from(u8::from(self.exists(address))) }
+		fn code_size(&self, _address: H160) -> U256 { U256::zero() }
+		fn code_hash(&self, _address: H160) -> H256 { H256::zero() }
+		fn code(&self, _address: H160) -> Vec<u8> { Vec::new() }
+		fn valids(&self, _address: H160) -> Vec<u8> { Vec::new() }
+		fn storage(&self, _address: H160, index: U256) -> U256 {
+			self.storage.get(&index).copied().unwrap_or_default()
+		}
+		fn original_storage(&self, _address: H160, index: U256) -> U256 {
+			self.original_storage.get(&index).copied().unwrap_or_default()
+		}
+		fn transient_storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+		fn is_cold(&self, address: H160, index: Option<U256>) -> bool { !self.warm.contains(&(address, index)) }
+		fn gas_left(&self) -> U256 { U256::zero() }
+		fn gas_price(&self) -> U256 { U256::zero() }
+		fn origin(&self) -> H160 { H160::zero() }
+		fn block_hash(&self, _number: U256) -> H256 { H256::zero() }
+		fn block_number(&self) -> U256 { U256::zero() }
+		fn block_coinbase(&self) -> H160 { H160::zero() }
+		fn block_timestamp(&self) -> U256 { U256::zero() }
+		fn block_difficulty(&self) -> U256 { U256::zero() }
+		fn block_gas_limit(&self) -> U256 { U256::zero() }
+		fn chain_id(&self) -> U256 { U256::zero() }
+		fn set_storage(&mut self, _address: H160, _index: U256, _value: U256) -> Result<(), ExitError> { Ok(()) }
+		fn set_transient_storage(&mut self, _address: H160, _index: U256, _value: U256) -> Result<(), ExitError> {
+			Ok(())
+		}
+		fn log(&mut self, _address: H160, _topics: Vec<H256>, _data: Vec<u8>) -> Result<(), ExitError> { Ok(()) }
+		fn mark_delete(&mut self, _address: H160, _target: H160) -> Result<(), ExitError> { Ok(()) }
+		fn create(
+			&mut self,
+			_caller: H160,
+			_scheme: CreateScheme,
+			_value: U256,
+			_init_code: Vec<u8>,
+			_target_gas: Option<u64>,
+			_depth: usize,
+			_config: &Config,
+		) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Infallible> {
+			Capture::Exit((ExitSucceed::Returned.into(), None, Vec::new()))
+		}
+		fn call(
+			&mut self,
+			_code_address: H160,
+			_transfer: Option<Transfer>,
+			_input: Vec<u8>,
+			_target_gas: Option<u64>,
+			_is_static: bool,
+			_context: Context,
+			_config: &Config,
+		) -> Capture<(ExitReason, Vec<u8>), Infallible> {
+			Capture::Exit((ExitSucceed::Returned.into(), Vec::new()))
+		}
+		fn pre_validate(
+			&mut self,
+			_context: &Context,
+			_opcode: Opcode,
+			_stack: &Stack,
+			_config: &Config,
+		) -> Result<(), ExitError> {
+			Ok(())
+		}
+		fn other(&mut self, _opcode: Opcode, _machine: &mut Machine, _config: &Config) -> Result<(), ExitFatal> {
+			Err(ExitFatal::NotSupported)
+		}
+	}
+
+	/// Stack holding `values`, the first one on top.
+	fn stack(values: &[U256]) -> Stack {
+		let mut stack = Stack::new(1024);
+		for value in values.iter().rev() {
+			stack.push_u256(*value).unwrap();
+		}
+		stack
+	}
+
+	/// Gas used by `opcode` with `values` on the stack, on a fresh gasometer.
+	fn cost(config: &Config, state: &State, opcode: Opcode, values: &[U256]) -> Result<u64, ExitError> {
+		let mut gasometer = Gasometer::new(100_000, config);
+		gasometer.record_opcode(H160::zero(), opcode, &stack(values), state)?;
+		Ok(gasometer.total_used_gas())
+	}
+
+	#[test]
+	fn test_storage_cost() {
+		let berlin = Config::berlin();
+		let mut state = State::default();
+		state.storage.insert(U256::one(), U256::one());
+		state.original_storage.insert(U256::one(), U256::one());
+		let slot = |index: u64| [U256::from(index)];
+		let store = |index: u64, value: u64| [U256::from(index), U256::from(value)];
+
+		assert_eq!(cost(&Config::istanbul(), &state, Opcode::SLOAD, &slot(0)), Ok(800));
+		assert_eq!(cost(&berlin, &state, Opcode::SLOAD, &slot(0)), Ok(2100));
+		// Setting a zero slot, resetting a non-zero one, and a no-op.
+		assert_eq!(cost(&berlin, &state, Opcode::SSTORE, &store(0, 1)), Ok(2100 + 20000));
+		assert_eq!(cost(&berlin, &state, Opcode::SSTORE, &store(1, 2)), Ok(2100 + 2900));
+		assert_eq!(cost(&berlin, &state, Opcode::SSTORE, &store(1, 1)), Ok(2100 + 100));
+
+		state.warm.push((H160::zero(), Some(U256::one())));
+		assert_eq!(cost(&berlin, &state, Opcode::SLOAD, &slot(1)), Ok(100));
+		assert_eq!(cost(&berlin, &state, Opcode::SSTORE, &store(1, 2)), Ok(2900));
+		assert_eq!(cost(&Config::frontier(), &state, Opcode::SSTORE, &store(0, 1)), Ok(20000));
+
+		// Clearing a slot is refunded, and SSTORE fails within the stipend.
+		let mut gasometer = Gasometer::new(100_000, &berlin);
+		let target = gasometer.record_opcode(H160::zero(), Opcode::SSTORE, &stack(&store(1, 0)), &state);
+		assert_eq!(target, Ok(StorageTarget::Slot(H160::zero(), U256::one())));
+		assert_eq!(gasometer.refunded_gas(), 15000);
+		let mut gasometer = Gasometer::new(2300, &berlin);
+		assert_eq!(
+			gasometer.record_opcode(H160::zero(), Opcode::SSTORE, &stack(&store(1, 0)), &state),
+			Err(ExitError::OutOfGas),
+		);
+	}
+
+	#[test]
+	fn test_exp_cost() {
+		let state = State::default();
+		let exp = |power: u64| [U256::from(2), U256::from(power)];
+
+		assert_eq!(cost(&Config::cancun(), &state, Opcode::EXP, &exp(0)), Ok(10));
+		assert_eq!(cost(&Config::cancun(), &state, Opcode::EXP, &exp(0x100)), Ok(10 + 2 * 50));
+		assert_eq!(cost(&Config::frontier(), &state, Opcode::EXP, &exp(0x100)), Ok(10 + 2 * 10));
+		assert_eq!(cost(&Config::cancun(), &state, Opcode::EXP, &[U256::from(2), U256::MAX]), Ok(10 + 32 * 50));
+	}
+
+	#[test]
+	fn test_memory_expansion_cost() {
+		let config = Config::cancun();
+		let state = State::default();
+		let mstore = |offset: u64| stack(&[U256::from(offset), U256::zero()]);
+		let mut gasometer = Gasometer::new(100_000, &config);
+		let mut record = |offset: u64| {
+			let used_gas = gasometer.total_used_gas();
+			gasometer.record_opcode(H160::zero(), Opcode::MSTORE, &mstore(offset), &state).unwrap();
+			gasometer.total_used_gas() - used_gas
+		};
+
+		// Expanding to one word, then within it, then to 33 words.
+		assert_eq!(record(0), 3 + 3);
+		assert_eq!(record(1), 3 + 3);
+		assert_eq!(record(0), 3);
+		assert_eq!(record(1024), 3 + (3 * 33 + 33 * 33 / 512) - 6);
+		let too_far = [U256::from(u64::MAX), U256::zero()];
+		assert_eq!(cost(&config, &state, Opcode::MSTORE, &too_far), Err(ExitError::OutOfGas));
+	}
+
+	#[test]
+	fn test_call_cost() {
+		let berlin = Config::berlin();
+		let existing = H160::repeat_byte(1);
+		let mut state = State { existing: vec![existing], ..State::default() };
+		// Gas, target, value, and empty input and output.
+		let call = |target: H160, value: u64| {
+			let target = U256::from_big_endian(H256::from(target).as_bytes());
+			[U256::from(1000), target, U256::from(value), U256::zero(), U256::zero(), U256::zero(), U256::zero()]
+		};
+		let delegate = |target: H160| {
+			let mut values = call(target, 0).to_vec();
+			values.remove(2);
+			values
+		};
+
+		assert_eq!(cost(&berlin, &state, Opcode::CALL, &call(existing, 0)), Ok(2600));
+		assert_eq!(cost(&berlin, &state, Opcode::CALL, &call(existing, 1)), Ok(2600 + 9000));
+		assert_eq!(cost(&berlin, &state, Opcode::CALL, &call(H160::zero(), 1)), Ok(2600 + 9000 + 25000));
+		// Only CALL creates the target, and without value only before EIP-161.
+		assert_eq!(cost(&berlin, &state, Opcode::CALL, &call(H160::zero(), 0)), Ok(2600));
+		assert_eq!(cost(&berlin, &state, Opcode::CALLCODE, &call(H160::zero(), 1)), Ok(2600 + 9000));
+		assert_eq!(cost(&Config::frontier(), &state, Opcode::CALL, &call(H160::zero(), 0)), Ok(40 + 25000));
+		assert_eq!(cost(&Config::istanbul(), &state, Opcode::CALL, &call(existing, 0)), Ok(700));
+
+		state.warm.push((existing, None));
+		assert_eq!(cost(&berlin, &state, Opcode::DELEGATECALL, &delegate(existing)), Ok(100));
+		assert_eq!(cost(&berlin, &state, Opcode::STATICCALL, &delegate(existing)), Ok(100));
+		assert_eq!(cost(&berlin, &state, Opcode::STATICCALL, &delegate(H160::zero())), Ok(2600));
+
+		// Expanding memory for the output.
+		let mut values = call(existing, 0);
+		values[6] = U256::from(32);
+		assert_eq!(cost(&berlin, &state, Opcode::CALL, &values), Ok(100 + 3));
+	}
+
+	#[test]
+	fn test_transaction_cost() {
+		let mut config = Config::istanbul();
+		let access_list = [(H160::zero(), vec![U256::zero(), U256::one()])];
+
+		let cost = call_transaction_cost(&[0, 1, 2], &[], 0);
+		assert_eq!(cost.cost(&config), Ok(21036));
+		assert_eq!(cost.floor_cost(&config), 0);
+
+		config.gas_access_list_address = 2400;
+		config.gas_access_list_storage_key = 1900;
+		config.total_cost_floor_per_token = 10;
+		let cost = call_transaction_cost(&[0, 1, 2], &access_list, 0);
+		assert_eq!(cost.cost(&config), Ok(21036 + 2400 + 2 * 1900));
+		assert_eq!(cost.floor_cost(&config), 21090);
+
+		config.max_initcode_size = Some(32);
+		assert_eq!(create_transaction_cost(&[1; 33], &[]).cost(&config), Err(ExitError::CreateContractLimit));
+		assert_eq!(create_transaction_cost(&[1; 32], &[]).cost(&config), Ok(53000 + 32 * 16 + 2));
+	}
+}
@@ -0,0 +1,182 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+use crate::{Config, H160, U256};
+
+/// An entry added to the access set, kept so that it can be reverted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+enum Access {
+	Address(H160),
+	Storage(H160, U256),
+}
+
+/// Addresses and storage slots accessed in a transaction (EIP-2929).
+///
+/// Accesses are journaled per call frame: `enter` opens a frame, and
+/// `exit_revert` forgets every access made since, while `exit_commit` keeps
+/// them for the enclosing frame.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct Accessed {
+	addresses: BTreeSet<H160>,
+	storage: BTreeSet<(H160, U256)>,
+	journal: Vec<Access>,
+	checkpoints: Vec<usize>,
+}
+
+impl Accessed {
+	/// Create an empty access set.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			addresses: BTreeSet::new(),
+			storage: BTreeSet::new(),
+			journal: Vec::new(),
+			checkpoints: Vec::new(),
+		}
+	}
+
+	/// Create the access set of a transaction from `caller` to `address`,
+	/// pre-warming both of them, the precompiles, the block coinbase (if
+	/// EIP-3651 is enabled) and the transaction access list (EIP-2930).
+	pub fn for_transaction<I>(
+		config: &Config,
+		caller: H160,
+		address: H160,
+		coinbase: H160,
+		precompiles: I,
+		access_list: &[(H160, Vec<U256>)],
+	) -> Self
+		where I: IntoIterator<Item = H160>
+	{
+		let mut accessed = Self::new();
+
+		accessed.addresses.insert(caller);
+		accessed.addresses.insert(address);
+		if config.warm_coinbase_address {
+			accessed.addresses.insert(coinbase);
+		}
+		accessed.addresses.extend(precompiles);
+
+		for (address, indexes) in access_list {
+			accessed.addresses.insert(*address);
+			accessed.storage.extend(indexes.iter().map(|index| (*address, *index)));
+		}
+
+		accessed
+	}
+
+	/// Check whether an address, or a storage slot of it if `index` is given,
+	/// has not been accessed yet.
+	#[must_use]
+	pub fn is_cold(&self, address: H160, index: Option<U256>) -> bool {
+		index.map_or_else(
+			|| !self.addresses.contains(&address),
+			|index| !self.storage.contains(&(address, index)),
+		)
+	}
+
+	/// Mark an address as accessed.
+	pub fn access_address(&mut self, address: H160) {
+		if self.addresses.insert(address) {
+			self.journal.push(Access::Address(address));
+		}
+	}
+
+	/// Mark a storage slot as accessed.
+	pub fn access_storage(&mut self, address: H160, index: U256) {
+		if self.storage.insert((address, index)) {
+			self.journal.push(Access::Storage(address, index));
+		}
+	}
+
+	/// Open a new call frame.
+	pub fn enter(&mut self) {
+		self.checkpoints.push(self.journal.len());
+	}
+
+	/// Close the current call frame, keeping its accesses.
+	pub fn exit_commit(&mut self) {
+		self.checkpoints.pop();
+	}
+
+	/// Close the current call frame, forgetting its accesses.
+	pub fn exit_revert(&mut self) {
+		let checkpoint = self.checkpoints.pop().unwrap_or(0);
+
+		for access in self.journal.drain(checkpoint..) {
+			match access {
+				Access::Address(address) => { self.addresses.remove(&address); },
+				Access::Storage(address, index) => { self.storage.remove(&(address, index)); },
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Accessed;
+	use crate::{Config, H160, U256};
+
+	#[test]
+	fn test_exit_revert() {
+		let address = H160::repeat_byte(1);
+		let mut accessed = Accessed::new();
+		accessed.access_address(address);
+
+		accessed.enter();
+		accessed.access_address(address);
+		accessed.access_address(H160::repeat_byte(2));
+		accessed.access_storage(address, U256::one());
+		accessed.exit_revert();
+
+		// Accesses made before the frame, even if repeated in it, stay warm.
+		assert!(!accessed.is_cold(address, None));
+		assert!(accessed.is_cold(H160::repeat_byte(2), None));
+		assert!(accessed.is_cold(address, Some(U256::one())));
+	}
+
+	#[test]
+	fn test_exit_commit() {
+		let address = H160::repeat_byte(1);
+		let mut accessed = Accessed::new();
+
+		accessed.enter();
+		accessed.enter();
+		accessed.access_address(address);
+		accessed.access_storage(address, U256::one());
+		accessed.exit_commit();
+		assert!(!accessed.is_cold(address, None));
+		assert!(!accessed.is_cold(address, Some(U256::one())));
+
+		// Committed accesses belong to the parent, and revert with it.
+		accessed.exit_revert();
+		assert!(accessed.is_cold(address, None));
+		assert!(accessed.is_cold(address, Some(U256::one())));
+	}
+
+	#[test]
+	fn test_for_transaction() {
+		let [caller, target, coinbase, precompile, listed] = [1, 2, 3, 4, 5].map(H160::repeat_byte);
+		let access_list = [(listed, vec![U256::one()])];
+
+		let accessed = Accessed::for_transaction(
+			&Config::shanghai(), caller, target, coinbase, vec![precompile], &access_list,
+		);
+		for address in [caller, target, coinbase, precompile, listed] {
+			assert!(!accessed.is_cold(address, None));
+		}
+		assert!(!accessed.is_cold(listed, Some(U256::one())));
+		assert!(accessed.is_cold(listed, Some(U256::zero())));
+		assert!(accessed.is_cold(H160::repeat_byte(6), None));
+
+		// The coinbase is only warm since EIP-3651.
+		let accessed = Accessed::for_transaction(
+			&Config::london(), caller, target, coinbase, vec![precompile], &access_list,
+		);
+		assert!(accessed.is_cold(coinbase, None));
+	}
+}
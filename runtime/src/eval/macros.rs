@@ -69,3 +69,12 @@ macro_rules! as_usize_or_fail {
 		}
 	};
 }
+
+/// fail with `StaticModeViolation` when the frame is static (EIP-214)
+macro_rules! fail_if_static {
+	( $runtime:expr ) => {
+		if $runtime.context.is_static {
+			return Control::Exit(ExitError::StaticModeViolation.into())
+		}
+	};
+}
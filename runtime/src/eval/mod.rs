@@ -21,7 +21,8 @@ pub enum Control<H: Handler> {
 fn handle_other<H: Handler>(state: &mut Runtime, opcode: Opcode, handler: &mut H) -> Control<H> {
 	match handler.other(
 		opcode,
-		&mut state.machine
+		&mut state.machine,
+		&state.config,
 	) {
 		Ok(()) => Control::Continue,
 		Err(e) => Control::Exit(e.into()),
@@ -61,7 +62,7 @@ pub fn eval<H: Handler>(state: &mut Runtime, opcode: Opcode, handler: &mut H) ->
 		Opcode::LOG2 => system::log(state, 2, handler),
 		Opcode::LOG3 => system::log(state, 3, handler),
 		Opcode::LOG4 => system::log(state, 4, handler),
-		// Opcode::SUICIDE => system::suicide(state, handler),
+		Opcode::SUICIDE => system::suicide(state, handler),
 		Opcode::CREATE => system::create(state, false, handler),
 		Opcode::CREATE2 => system::create(state, true, handler),
 		Opcode::CALL => system::call(state, CallScheme::Call, handler),
@@ -1,6 +1,6 @@
 use core::cmp::min;
 use alloc::vec::Vec;
-use crate::{Runtime, ExitError, Handler, Capture, Transfer, ExitReason, CreateScheme, CallScheme, Context, ExitSucceed, ExitFatal, H160, H256, U256};
+use crate::{Runtime, ExitError, Handler, Capture, Transfer, ExitReason, CreateScheme, CallScheme, Context, ExitSucceed, ExitFatal, H160, H256, U256, Config};
 use super::Control;
 
 /// Compute Keccak-256 hash
@@ -212,6 +212,7 @@ pub fn sload<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 
 /// Save word to storage
 pub fn sstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H> {
+	fail_if_static!(runtime);
 	pop_u256!(runtime, index, value);
 
 	match handler.set_storage(runtime.context.address, index, value) {
@@ -231,6 +232,7 @@ pub fn tload<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 
 /// Save word to transient storage
 pub fn tstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H> {
+	fail_if_static!(runtime);
 	pop_u256!(runtime, index, value);
 
 	match handler.set_transient_storage(runtime.context.address, index, value) {
@@ -250,6 +252,7 @@ pub fn gas<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 
 /// Append log record
 pub fn log<H: Handler>(runtime: &mut Runtime, n: u8, handler: &mut H) -> Control<H> {
+	fail_if_static!(runtime);
 	pop_u256!(runtime, offset, len);
 	let offset = as_usize_or_fail!(offset);
 	let len = as_usize_or_fail!(len);
@@ -275,9 +278,11 @@ pub fn log<H: Handler>(runtime: &mut Runtime, n: u8, handler: &mut H) -> Control
 	}
 }
 
-// Halt execution and register account for later deletion or send all Ether to address (post-Cancun)
-#[allow(dead_code)]
+/// Halt execution, sending the whole balance to the target and registering
+/// the account for deletion, or only the former since EIP-6780 if the
+/// account was not created in the same transaction.
 pub fn suicide<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H> {
+	fail_if_static!(runtime);
 	pop!(runtime, target);
 
 	match handler.mark_delete(runtime.context.address, target.into()) {
@@ -288,18 +293,52 @@ pub fn suicide<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H>
 	Control::Exit(ExitSucceed::Suicided.into())
 }
 
+/// Gas available to a sub-call or create after the current opcode has been
+/// charged: all but one 64th of the remaining gas (EIP-150).
+fn after_gas(gas_left: U256, config: &Config) -> U256 {
+	if config.call_l64_after_gas {
+		gas_left - gas_left / 64
+	} else {
+		gas_left
+	}
+}
+
+/// Gas forwarded to a sub-call requesting `gas`.
+fn forwarded_gas(gas: U256, gas_left: U256, config: &Config) -> Result<U256, ExitError> {
+	let after_gas = after_gas(gas_left, config);
+
+	if config.err_on_call_with_more_gas && gas > after_gas {
+		return Err(ExitError::OutOfGas)
+	}
+
+	Ok(min(gas, after_gas))
+}
+
+fn as_target_gas(gas: U256) -> Option<u64> {
+	if gas > U256::from(u64::MAX) {
+		None
+	} else {
+		Some(gas.as_u64())
+	}
+}
+
 /// Create a new account with associated code
 pub fn create<H: Handler>(
 	runtime: &mut Runtime,
 	is_create2: bool,
 	handler: &mut H,
 ) -> Control<H> {
+	fail_if_static!(runtime);
 	runtime.return_data_buffer = Vec::new();
 
 	pop_u256!(runtime, value, code_offset, len);
 	let code_offset = as_usize_or_fail!(code_offset);
 	let len = as_usize_or_fail!(len);
 
+	if runtime.config.max_initcode_size.is_some_and(|max| len > max) {
+		return Control::Exit(ExitError::CreateContractLimit.into())
+	}
+
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(code_offset, len));
 	let code = if len == 0 {
 		Vec::new()
@@ -322,7 +361,14 @@ pub fn create<H: Handler>(
 		}
 	};
 
-	match handler.create(runtime.context.address, scheme, value, code, None) {
+	let depth = runtime.context.depth + 1;
+	if depth > runtime.config.call_stack_limit {
+		return save_created_address(runtime, ExitError::CallTooDeep.into(), None)
+	}
+
+	let target_gas = as_target_gas(after_gas(handler.gas_left(), &runtime.config));
+
+	match handler.create(runtime.context.address, scheme, value, code, target_gas, depth, &runtime.config) {
 		Capture::Exit((reason, address, _return_data)) => {
 			save_created_address(runtime, reason, address)
 		},
@@ -344,14 +390,16 @@ pub fn call<'config, H: Handler>(
 
 	pop_u256!(runtime, gas);
 	pop!(runtime, to);
-	let gas = if gas > U256::from(u64::MAX) {
-		None
-	} else {
-		Some(gas.as_u64())
-	};
 
 	let value = match scheme {
-		CallScheme::Call | CallScheme::CallCode => {
+		CallScheme::Call => {
+			pop_u256!(runtime, value);
+			if !value.is_zero() {
+				fail_if_static!(runtime);
+			}
+			value
+		},
+		CallScheme::CallCode => {
 			pop_u256!(runtime, value);
 			value
 		},
@@ -360,6 +408,14 @@ pub fn call<'config, H: Handler>(
 		},
 	};
 
+	let gas = try_or_fail!(forwarded_gas(gas, handler.gas_left(), &runtime.config));
+	let gas = if value.is_zero() {
+		gas
+	} else {
+		gas.saturating_add(U256::from(runtime.config.call_stipend))
+	};
+	let target_gas = as_target_gas(gas);
+
 	// out_offset and out_len parameters will be read in save_return_value()
 	pop_u256!(runtime, in_offset, in_len/*, out_offset, out_len*/);
 	let in_offset = as_usize_or_fail!(in_offset);
@@ -374,21 +430,33 @@ pub fn call<'config, H: Handler>(
 		runtime.machine.memory().get(in_offset, in_len)
 	};
 
+	let depth = runtime.context.depth + 1;
+	if depth > runtime.config.call_stack_limit {
+		return save_return_value(runtime, ExitError::CallTooDeep.into(), Vec::new())
+	}
+
+	let is_static = runtime.context.is_static || scheme == CallScheme::StaticCall;
 	let context = match scheme {
 		CallScheme::Call | CallScheme::StaticCall => Context {
 			address: to.into(),
 			caller: runtime.context.address,
 			apparent_value: value,
+			depth,
+			is_static,
 		},
 		CallScheme::CallCode => Context {
 			address: runtime.context.address,
 			caller: runtime.context.address,
 			apparent_value: value,
+			depth,
+			is_static,
 		},
 		CallScheme::DelegateCall => Context {
 			address: runtime.context.address,
 			caller: runtime.context.caller,
 			apparent_value: runtime.context.apparent_value,
+			depth,
+			is_static,
 		},
 	};
 
@@ -408,7 +476,7 @@ pub fn call<'config, H: Handler>(
 		None
 	};
 
-	match handler.call(to.into(), transfer, input, gas, scheme == CallScheme::StaticCall, context) {
+	match handler.call(to.into(), transfer, input, target_gas, is_static, context, &runtime.config) {
 		Capture::Exit((reason, return_data)) => {
 			save_return_value(runtime, reason, return_data)
 		},
@@ -512,3 +580,100 @@ pub fn save_return_value<'config, H: Handler>(
 			}
         }
 }
+
+#[cfg(test)]
+mod tests {
+	use core::convert::TryFrom;
+	use super::{after_gas, forwarded_gas};
+	use crate::mock::{context, MockHandler};
+	use crate::{Config, Context, ExitError, U256};
+
+	/// CALL the zero address with 1000 gas and `value`.
+	fn call(value: u8) -> Vec<u8> {
+		vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, value, 0x60, 0x00, 0x61, 0x03, 0xe8, 0xf1]
+	}
+
+	#[test]
+	fn test_forwarded_gas() {
+		let byzantium = Config::byzantium();
+		let frontier = Config::frontier();
+
+		assert_eq!(after_gas(U256::from(6400), &byzantium), U256::from(6300));
+		assert_eq!(after_gas(U256::from(6400), &frontier), U256::from(6400));
+
+		// Since EIP-150, requesting more than is available forwards all but
+		// one 64th, while it used to fail.
+		assert_eq!(forwarded_gas(U256::from(100), U256::from(6400), &byzantium), Ok(U256::from(100)));
+		assert_eq!(forwarded_gas(U256::MAX, U256::from(6400), &byzantium), Ok(U256::from(6300)));
+		assert_eq!(forwarded_gas(U256::from(100), U256::from(6400), &frontier), Ok(U256::from(100)));
+		assert_eq!(forwarded_gas(U256::from(6401), U256::from(6400), &frontier), Err(ExitError::OutOfGas));
+	}
+
+	#[test]
+	fn test_call_stipend() {
+		let mut handler = MockHandler { gas_left: U256::from(6400), ..MockHandler::default() };
+		// CALL with value 1, then with value 0.
+		let mut code = [call(1), call(0)].concat();
+		// CREATE with empty init code.
+		code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x00]);
+
+		assert!(handler.run(code, context(false), &Config::byzantium()).is_succeed());
+		assert_eq!(handler.calls, [(Some(1000 + 2300), false), (Some(1000), false)]);
+		assert_eq!(handler.creates, [Some(6300)]);
+	}
+
+	#[test]
+	fn test_static_mode() {
+		let codes = [
+			// SSTORE, TSTORE, LOG0, CREATE, SELFDESTRUCT, and CALL with value.
+			vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00],
+			vec![0x60, 0x01, 0x60, 0x00, 0x5d, 0x00],
+			vec![0x60, 0x00, 0x60, 0x00, 0xa0, 0x00],
+			vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0, 0x00],
+			vec![0x60, 0x00, 0xff],
+			call(1),
+		];
+
+		for code in codes {
+			let mut handler = MockHandler::default();
+			assert!(handler.run(code.clone(), context(false), &Config::cancun()).is_succeed());
+
+			let mut handler = MockHandler::default();
+			let reason = handler.run(code, context(true), &Config::cancun());
+			assert_eq!(reason, ExitError::StaticModeViolation.into());
+			assert_eq!((handler.changes, handler.calls.len(), handler.creates.len()), (0, 0, 0));
+		}
+
+		// CALL without value is allowed, and the callee inherits static mode.
+		let mut handler = MockHandler::default();
+		assert!(handler.run(call(0), context(true), &Config::cancun()).is_succeed());
+		assert_eq!(handler.calls, [(Some(0), true)]);
+	}
+
+	#[test]
+	fn test_call_too_deep() {
+		// Fail with INVALID unless the top of the stack is zero.
+		let zero_or_invalid = |mut code: Vec<u8>| {
+			let jumpdest = u8::try_from(code.len() + 5).unwrap();
+			code.extend_from_slice(&[0x15, 0x60, jumpdest, 0x57, 0xfe, 0x5b, 0x00]);
+			code
+		};
+		// CREATE with empty init code.
+		let create = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf0];
+		let config = Config { call_stack_limit: 2, ..Config::cancun() };
+
+		// At the limit, CALL and CREATE push zero without entering a frame.
+		for code in [call(0), create.clone()] {
+			let mut handler = MockHandler::default();
+			let context = Context { depth: 2, ..context(false) };
+			assert!(handler.run(zero_or_invalid(code), context, &config).is_succeed());
+			assert_eq!((handler.calls.len(), handler.creates.len()), (0, 0));
+		}
+
+		// Below it, they are handed to the handler.
+		let mut handler = MockHandler::default();
+		let context = Context { depth: 1, ..context(false) };
+		assert!(handler.run([call(0), create].concat(), context, &config).is_succeed());
+		assert_eq!((handler.calls.len(), handler.creates.len()), (1, 1));
+	}
+}
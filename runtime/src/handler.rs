@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 use crate::{Capture, Stack, ExitError, Opcode,
-			Machine, ExitReason,
+			Machine, ExitReason, Config,
 			H160, H256, U256};
 use evm_core::{Context, CreateScheme, ExitFatal, Transfer};
 
@@ -20,6 +20,8 @@ pub trait Handler {
 
 	/// Get account nonce
 	fn nonce(&self, address: H160) -> U256;
+	/// Check whether an address exists.
+	fn exists(&self, address: H160) -> bool;
 	/// Get balance of address.
 	fn balance(&self, address: H160) -> U256;
 	/// Get code size of address.
@@ -32,7 +34,13 @@ pub trait Handler {
 	fn valids(&self, address: H160) -> Vec<u8>;
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: U256) -> U256;
+	/// Get storage value of address at index as it was at the beginning of
+	/// the transaction.
+	fn original_storage(&self, address: H160, index: U256) -> U256;
 	fn transient_storage(&self, address: H160, index: U256) -> U256;
+	/// Check whether an address, or a storage slot of it if `index` is given,
+	/// has not been accessed yet in the transaction (EIP-2929).
+	fn is_cold(&self, address: H160, index: Option<U256>) -> bool;
 
 	/// Get the gas left value.
 	fn gas_left(&self) -> U256;
@@ -60,9 +68,17 @@ pub trait Handler {
 	fn set_transient_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError>;
 	/// Create a log owned by address with given topics and data.
 	fn log(&mut self, address: H160, topcis: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError>;
-	/// Mark an address to be deleted, with funds transferred to target.
+	/// Handle SELFDESTRUCT: transfer the balance of `address` to `target`,
+	/// and mark it to be deleted at the end of the transaction, unless
+	/// `has_restricted_selfdestruct` is set and it was not created in the
+	/// same transaction (EIP-6780).
 	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError>;
-	/// Invoke a create operation.
+	/// Invoke a create operation. `target_gas` is the gas available to the
+	/// init code, all but one 64th of the remaining gas since EIP-150, or
+	/// `None` if it does not fit in a `u64`. `depth` is the call depth of
+	/// the init code frame, within `call_stack_limit`. `config` is the
+	/// configuration of the calling runtime.
+	#[allow(clippy::too_many_arguments)]
 	fn create(
 		&mut self,
 		caller: H160,
@@ -70,6 +86,8 @@ pub trait Handler {
 		value: U256,
 		init_code: Vec<u8>,
 		target_gas: Option<u64>,
+		depth: usize,
+		config: &Config,
 	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Self::CreateInterrupt>;
 	/// Feed in create feedback.
 	fn create_feedback(
@@ -78,7 +96,17 @@ pub trait Handler {
 	) -> Result<(), ExitError> {
 		Ok(())
 	}
-	/// Invoke a call operation.
+	/// Invoke a call operation. `target_gas` is the gas forwarded to the
+	/// callee, including the call stipend when value is transferred, or
+	/// `None` if it does not fit in a `u64`. The current frame pays for the
+	/// forwarded gas less the stipend, and gets back whatever the callee
+	/// leaves unused when it returns. In estimate mode the gas withheld from
+	/// the callee for it to receive what it used should be counted as well,
+	/// see `Gasometer::record_call_reserve`. `context.depth` is the call
+	/// depth of the callee, within `call_stack_limit`, and `is_static` is
+	/// `context.is_static`. `config` is the configuration of the calling
+	/// runtime.
+	#[allow(clippy::too_many_arguments)]
 	fn call(
 		&mut self,
 		code_address: H160,
@@ -87,6 +115,7 @@ pub trait Handler {
 		target_gas: Option<u64>,
 		is_static: bool,
 		context: Context,
+		config: &Config,
 	) -> Capture<(ExitReason, Vec<u8>), Self::CallInterrupt>;
 	/// Feed in call feedback.
 	fn call_feedback(
@@ -96,17 +125,19 @@ pub trait Handler {
 		Ok(())
 	}
 
-	/// Pre-validation step for the runtime.
+	/// Pre-validation step for the runtime, under the runtime's `config`.
 	fn pre_validate(
 		&mut self,
 		context: &Context,
 		opcode: Opcode,
-		stack: &Stack
+		stack: &Stack,
+		config: &Config,
 	) -> Result<(), ExitError>;
 	/// Handle other unknown external opcodes.
 	fn other(
 		&mut self,
 		_opcode: Opcode,
-		_stack: &mut Machine
+		_stack: &mut Machine,
+		_config: &Config,
 	) -> Result<(), ExitFatal>;
 }
@@ -17,6 +17,12 @@ impl<'a> ResolveCreate<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
 		Self { runtime }
 	}
+
+	/// Release the runtime without failing it, to be resumed with
+	/// `save_created_address` once the create has exited.
+	pub const fn release(self) {
+		core::mem::forget(self);
+	}
 }
 
 impl<'a> Drop for ResolveCreate<'a> {
@@ -35,6 +41,12 @@ impl<'a> ResolveCall<'a> {
 	pub(crate) fn new(runtime: &'a mut Runtime) -> Self {
 		Self { runtime }
 	}
+
+	/// Release the runtime without failing it, to be resumed with
+	/// `save_return_value` once the call has exited.
+	pub const fn release(self) {
+		core::mem::forget(self);
+	}
 }
 
 impl<'a> Drop for ResolveCall<'a> {
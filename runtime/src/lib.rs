@@ -17,14 +17,20 @@ extern crate alloc;
 mod eval;
 mod interrupt;
 mod handler;
+mod accessed;
+mod transient;
+#[cfg(test)]
+mod mock;
 
 pub use evm_core::{
 	Machine, Transfer, ExitReason, Context, Capture, Stack, ExitError, CreateScheme, CallScheme,
-	ExitSucceed, ExitFatal, H160, H256, U256, Opcode,
+	ExitSucceed, ExitFatal, H160, H256, U256, Opcode, OpcodeSet, StepCosts,
 };
 
 pub use crate::interrupt::{Resolve, ResolveCall, ResolveCreate};
 pub use crate::handler::Handler;
+pub use crate::accessed::Accessed;
+pub use crate::transient::TransientStorage;
 pub use crate::eval::{save_return_value, save_created_address, Control};
 
 use alloc::vec::Vec;
@@ -41,24 +47,59 @@ pub struct Runtime {
 	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
 	return_data_buffer: Vec<u8>,
 	context: Context,
+	config: Config,
 }
 
 impl Runtime {
-	/// Create a new runtime with given code and data.
+	/// Create a new runtime with given code and data, under the default
+	/// configuration. Every implemented opcode is enabled, use
+	/// `new_with_config` to reject those the configuration disables.
 	pub fn new(
 		code: Vec<u8>,
 		valids: Vec<u8>,
 		data: Vec<u8>,
 		context: Context,
 	) -> Self {
+		let machine = Machine::new(code, valids, data, CONFIG.stack_limit, CONFIG.memory_limit);
+		Self::with_machine(machine, context, &CONFIG)
+	}
+
+	/// Create a new runtime with given code, data and configuration.
+	#[must_use]
+	pub fn new_with_config(
+		code: Vec<u8>,
+		valids: Vec<u8>,
+		data: Vec<u8>,
+		context: Context,
+		config: &Config,
+	) -> Self {
+		let machine = Machine::new_with_disabled_opcodes(
+			code,
+			valids,
+			data,
+			config.stack_limit,
+			config.memory_limit,
+			config.disabled_opcodes(),
+		);
+		Self::with_machine(machine, context, config)
+	}
+
+	fn with_machine(machine: Machine, context: Context, config: &Config) -> Self {
 		Self {
-			machine: Machine::new(code, valids, data, CONFIG.stack_limit, CONFIG.memory_limit),
+			machine,
 			status: Ok(()),
 			return_data_buffer: Vec::new(),
 			context,
+			config: config.clone(),
 		}
 	}
 
+	/// Get the configuration of this runtime.
+	#[must_use]
+	pub const fn config(&self) -> &Config {
+		&self.config
+	}
+
 	/// Get return data
 	pub fn return_data(&self) -> &Vec<u8> {
 		&self.return_data_buffer
@@ -74,11 +115,23 @@ impl Runtime {
 		&self.machine
 	}
 
-	/// Loop stepping the runtime until it stops.
+	/// Loop stepping the runtime until it stops, or the budget of
+	/// `max_steps` runs out under the default `StepCosts`.
 	pub fn run<'a, H: Handler>(
 		&'a mut self,
 		max_steps: u64,
 		handler: &mut H,
+	) -> (u64, Capture<ExitReason, Resolve<'a, H>>) {
+		self.run_with_step_costs(max_steps, &StepCosts::DEFAULT, handler)
+	}
+
+	/// Loop stepping the runtime until it stops, or the budget of
+	/// `max_steps` runs out with opcodes weighted by `step_costs`.
+	pub fn run_with_step_costs<'a, H: Handler>(
+		&'a mut self,
+		max_steps: u64,
+		step_costs: &StepCosts,
+		handler: &mut H,
 	) -> (u64, Capture<ExitReason, Resolve<'a, H>>) {
 		if let Err(e) = self.status {
 			return (0, Capture::Exit(e));
@@ -89,8 +142,9 @@ impl Runtime {
 		while steps < max_steps {
 			let (steps_executed, capture) = {
 				let context = &self.context;
-				let pre_validate = |opcode, stack: &Stack| { handler.pre_validate(context, opcode, stack) };
-				self.machine.run(max_steps - steps, pre_validate, &self.context)
+				let config = &self.config;
+				let pre_validate = |opcode, stack: &Stack| { handler.pre_validate(context, opcode, stack, config) };
+				self.machine.run_with_step_costs(max_steps - steps, step_costs, pre_validate, &self.context)
 			};
 			steps += steps_executed;
 
@@ -129,6 +183,9 @@ impl Runtime {
 
 /// Runtime configuration.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
 pub struct Config {
 	/// Gas paid for extcode.
 	pub gas_ext_code: u64,
@@ -140,10 +197,20 @@ pub struct Config {
 	pub gas_sstore_reset: u64,
 	/// Gas paid for sstore refund.
 	pub refund_sstore_clears: i64,
+	/// Gas refunded for the first SELFDESTRUCT of an account.
+	pub refund_suicide: i64,
+	/// Maximum refund, as a quotient of the gas used by the transaction.
+	pub max_refund_quotient: u64,
 	/// Gas paid for BALANCE opcode.
 	pub gas_balance: u64,
 	/// Gas paid for SLOAD opcode.
 	pub gas_sload: u64,
+	/// Gas paid for a cold SLOAD, or added to a cold SSTORE (EIP-2929).
+	pub gas_sload_cold: u64,
+	/// Gas paid for a warm storage or account access (EIP-2929).
+	pub gas_storage_read_warm: u64,
+	/// Gas paid for a cold account access (EIP-2929).
+	pub gas_account_access_cold: u64,
 	/// Gas paid for SUICIDE opcode.
 	pub gas_suicide: u64,
 	/// Gas paid for SUICIDE opcode when it hits a new account.
@@ -160,10 +227,40 @@ pub struct Config {
 	pub gas_transaction_zero_data: u64,
 	/// Gas paid for non-zero data in a transaction.
 	pub gas_transaction_non_zero_data: u64,
+	/// Gas paid for each address in a transaction access list (EIP-2930).
+	pub gas_access_list_address: u64,
+	/// Gas paid for each storage key in a transaction access list (EIP-2930).
+	pub gas_access_list_storage_key: u64,
+	/// Gas paid for each authorization in a transaction (EIP-7702).
+	pub gas_per_empty_account_cost: u64,
+	/// Gas paid for the `alt_bn128` addition precompile.
+	pub gas_bn128_add: u64,
+	/// Gas paid for the `alt_bn128` scalar multiplication precompile.
+	pub gas_bn128_mul: u64,
+	/// Gas paid for the `alt_bn128` pairing check precompile.
+	pub gas_bn128_pairing_base: u64,
+	/// Gas paid per pair checked by the `alt_bn128` pairing precompile.
+	pub gas_bn128_pairing_per_point: u64,
+	/// Gas paid per calldata token by the transaction floor (EIP-7623),
+	/// zero if the floor is not enforced.
+	pub total_cost_floor_per_token: u64,
+	/// Maximum size of the init code of a create transaction (EIP-3860),
+	/// whose words are also charged when set.
+	pub max_initcode_size: Option<usize>,
+	/// MODEXP pricing of EIP-2565, instead of EIP-198.
+	pub modexp_eip2565_pricing: bool,
+	/// MODEXP pricing of EIP-7883, instead of EIP-2565.
+	pub modexp_eip7883_pricing: bool,
+	/// Maximum length of each MODEXP operand (EIP-7823).
+	pub modexp_max_input_size: Option<usize>,
 	/// EIP-1283.
 	pub sstore_gas_metering: bool,
 	/// EIP-1706.
 	pub sstore_revert_under_stipend: bool,
+	/// EIP-2929.
+	pub increase_state_access_gas: bool,
+	/// EIP-3651.
+	pub warm_coinbase_address: bool,
 	/// Whether to throw out of gas error when
 	/// CALL/CALLCODE/DELEGATECALL requires more than maximum amount
 	/// of gas.
@@ -190,6 +287,8 @@ pub struct Config {
 	pub has_create2: bool,
 	/// Has revert.
 	pub has_revert: bool,
+	/// Has static call (EIP-214).
+	pub has_static_call: bool,
 	/// Has return data.
 	pub has_return_data: bool,
 	/// Has bitwise shifting.
@@ -200,56 +299,398 @@ pub struct Config {
 	pub has_self_balance: bool,
 	/// Has ext code hash.
 	pub has_ext_code_hash: bool,
-	/// Whether the gasometer is running in estimate mode.
+	/// Has base fee (EIP-3198).
+	pub has_base_fee: bool,
+	/// Has PUSH0 (EIP-3855).
+	pub has_push0: bool,
+	/// Has MCOPY (EIP-5656).
+	pub has_mcopy: bool,
+	/// Has transient storage (EIP-1153).
+	pub has_transient_storage: bool,
+	/// Whether SELFDESTRUCT only deletes contracts created in the same
+	/// transaction (EIP-6780).
+	pub has_restricted_selfdestruct: bool,
+	/// Whether transactions may carry an authorization list (EIP-7702).
+	pub has_authorization_list: bool,
+	/// Whether new contract code starting with 0xEF is rejected (EIP-3541).
+	pub disallow_executable_format: bool,
+	/// Maximum gas limit of a transaction (EIP-7825).
+	pub max_transaction_gas_limit: Option<u64>,
+	/// Whether the gasometer is running in estimate mode, ignoring refunds
+	/// and counting the gas EIP-150 withholds from sub-calls as used.
 	pub estimate: bool,
 }
 
 pub const CONFIG: Config = Config::istanbul();
 
 impl Config {
-	/// Istanbul hard fork configuration.
-	pub const fn istanbul() -> Config {
-		Config {
-			gas_ext_code: 700,
-			gas_ext_code_hash: 700,
-			gas_balance: 700,
-			gas_sload: 800,
+	/// Frontier hard fork configuration.
+	#[must_use]
+	pub const fn frontier() -> Self {
+		Self {
+			gas_ext_code: 20,
+			gas_ext_code_hash: 20,
+			gas_balance: 20,
+			gas_sload: 50,
+			gas_sload_cold: 0,
+			gas_storage_read_warm: 0,
+			gas_account_access_cold: 0,
 			gas_sstore_set: 20000,
 			gas_sstore_reset: 5000,
 			refund_sstore_clears: 15000,
+			refund_suicide: 24000,
+			max_refund_quotient: 2,
+			gas_suicide: 0,
+			gas_suicide_new_account: 0,
+			gas_call: 40,
+			gas_expbyte: 10,
+			gas_transaction_create: 21000,
+			gas_transaction_call: 21000,
+			gas_transaction_zero_data: 4,
+			gas_transaction_non_zero_data: 68,
+			gas_access_list_address: 0,
+			gas_access_list_storage_key: 0,
+			gas_per_empty_account_cost: 0,
+			gas_bn128_add: 500,
+			gas_bn128_mul: 40000,
+			gas_bn128_pairing_base: 100_000,
+			gas_bn128_pairing_per_point: 80000,
+			total_cost_floor_per_token: 0,
+			max_initcode_size: None,
+			modexp_eip2565_pricing: false,
+			modexp_eip7883_pricing: false,
+			modexp_max_input_size: None,
+			sstore_gas_metering: false,
+			sstore_revert_under_stipend: false,
+			increase_state_access_gas: false,
+			warm_coinbase_address: false,
+			err_on_call_with_more_gas: true,
+			empty_considered_exists: true,
+			create_increase_nonce: false,
+			call_l64_after_gas: false,
+			stack_limit: 1024,
+			memory_limit: usize::MAX,
+			call_stack_limit: 1024,
+			create_contract_limit: None,
+			call_stipend: 2300,
+			has_delegate_call: false,
+			has_create2: false,
+			has_revert: false,
+			has_static_call: false,
+			has_return_data: false,
+			has_bitwise_shifting: false,
+			has_chain_id: false,
+			has_self_balance: false,
+			has_ext_code_hash: false,
+			has_base_fee: false,
+			has_push0: false,
+			has_mcopy: false,
+			has_transient_storage: false,
+			has_restricted_selfdestruct: false,
+			has_authorization_list: false,
+			disallow_executable_format: false,
+			max_transaction_gas_limit: None,
+			estimate: false,
+		}
+	}
+
+	/// Homestead hard fork configuration (EIP-2, EIP-7).
+	#[must_use]
+	pub const fn homestead() -> Self {
+		Self {
+			gas_transaction_create: 53000,
+			has_delegate_call: true,
+			..Self::frontier()
+		}
+	}
+
+	/// Byzantium hard fork configuration, including Tangerine Whistle
+	/// (EIP-150) and Spurious Dragon (EIP-160, EIP-161, EIP-170).
+	#[must_use]
+	pub const fn byzantium() -> Self {
+		Self {
+			gas_ext_code: 700,
+			gas_balance: 400,
+			gas_sload: 200,
 			gas_suicide: 5000,
 			gas_suicide_new_account: 25000,
 			gas_call: 700,
 			gas_expbyte: 50,
-			gas_transaction_create: 53000,
-			gas_transaction_call: 21000,
-			gas_transaction_zero_data: 4,
-			gas_transaction_non_zero_data: 16,
-			sstore_gas_metering: true,
-			sstore_revert_under_stipend: true,
 			err_on_call_with_more_gas: false,
 			empty_considered_exists: false,
 			create_increase_nonce: true,
 			call_l64_after_gas: true,
-			stack_limit: 1024,
-			memory_limit: usize::max_value(),
-			call_stack_limit: 1024,
 			create_contract_limit: Some(0x6000),
-			call_stipend: 2300,
-			has_delegate_call: true,
-			has_create2: true,
 			has_revert: true,
+			has_static_call: true,
 			has_return_data: true,
+			..Self::homestead()
+		}
+	}
+
+	/// Constantinople hard fork configuration, as amended by Petersburg
+	/// (without EIP-1283).
+	#[must_use]
+	pub const fn constantinople() -> Self {
+		Self {
+			gas_ext_code_hash: 400,
+			has_create2: true,
 			has_bitwise_shifting: true,
+			has_ext_code_hash: true,
+			..Self::byzantium()
+		}
+	}
+
+	/// Istanbul hard fork configuration, with the `alt_bn128` repricing of
+	/// EIP-1108.
+	#[must_use]
+	pub const fn istanbul() -> Self {
+		Self {
+			gas_bn128_add: 150,
+			gas_bn128_mul: 6000,
+			gas_bn128_pairing_base: 45000,
+			gas_bn128_pairing_per_point: 34000,
+			gas_ext_code_hash: 700,
+			gas_balance: 700,
+			gas_sload: 800,
+			gas_transaction_non_zero_data: 16,
+			sstore_gas_metering: true,
+			sstore_revert_under_stipend: true,
 			has_chain_id: true,
 			has_self_balance: true,
-			has_ext_code_hash: true,
-			estimate: false,
+			..Self::constantinople()
+		}
+	}
+
+	/// Berlin hard fork configuration (EIP-2565, EIP-2929, EIP-2930).
+	#[must_use]
+	pub const fn berlin() -> Self {
+		Self {
+			gas_sload: 100,
+			gas_sload_cold: 2100,
+			gas_storage_read_warm: 100,
+			gas_account_access_cold: 2600,
+			gas_sstore_reset: 2900,
+			gas_access_list_address: 2400,
+			gas_access_list_storage_key: 1900,
+			increase_state_access_gas: true,
+			modexp_eip2565_pricing: true,
+			..Self::istanbul()
+		}
+	}
+
+	/// London hard fork configuration (EIP-3198, EIP-3529, EIP-3541).
+	#[must_use]
+	pub const fn london() -> Self {
+		Self {
+			refund_sstore_clears: 4800,
+			refund_suicide: 0,
+			max_refund_quotient: 5,
+			has_base_fee: true,
+			disallow_executable_format: true,
+			..Self::berlin()
+		}
+	}
+
+	/// Shanghai hard fork configuration (EIP-3651, EIP-3855, EIP-3860).
+	#[must_use]
+	pub const fn shanghai() -> Self {
+		Self {
+			warm_coinbase_address: true,
+			has_push0: true,
+			max_initcode_size: Some(0xC000),
+			..Self::london()
+		}
+	}
+
+	/// Cancun hard fork configuration (EIP-1153, EIP-5656, EIP-6780).
+	#[must_use]
+	pub const fn cancun() -> Self {
+		Self {
+			has_mcopy: true,
+			has_transient_storage: true,
+			has_restricted_selfdestruct: true,
+			..Self::shanghai()
 		}
 	}
 
+	/// Prague hard fork configuration (EIP-7623, EIP-7702).
+	#[must_use]
+	pub const fn prague() -> Self {
+		Self {
+			gas_per_empty_account_cost: 25000,
+			total_cost_floor_per_token: 10,
+			has_authorization_list: true,
+			..Self::cancun()
+		}
+	}
+
+	/// Osaka hard fork configuration (EIP-7823, EIP-7825, EIP-7883).
+	#[must_use]
+	pub const fn osaka() -> Self {
+		Self {
+			modexp_eip7883_pricing: true,
+			modexp_max_input_size: Some(1024),
+			max_transaction_gas_limit: Some(1 << 24),
+			..Self::prague()
+		}
+	}
+
+	/// Opcodes not available under this configuration.
+	#[must_use]
+	pub const fn disabled_opcodes(&self) -> OpcodeSet {
+		let mut disabled = OpcodeSet::new();
+
+		if !self.has_delegate_call {
+			disabled = disabled.with(Opcode::DELEGATECALL);
+		}
+		if !self.has_create2 {
+			disabled = disabled.with(Opcode::CREATE2);
+		}
+		if !self.has_revert {
+			disabled = disabled.with(Opcode::REVERT);
+		}
+		if !self.has_static_call {
+			disabled = disabled.with(Opcode::STATICCALL);
+		}
+		if !self.has_return_data {
+			disabled = disabled.with(Opcode::RETURNDATASIZE).with(Opcode::RETURNDATACOPY);
+		}
+		if !self.has_bitwise_shifting {
+			disabled = disabled.with(Opcode::SHL).with(Opcode::SHR).with(Opcode::SAR);
+		}
+		if !self.has_chain_id {
+			disabled = disabled.with(Opcode::CHAINID);
+		}
+		if !self.has_self_balance {
+			disabled = disabled.with(Opcode::SELFBALANCE);
+		}
+		if !self.has_ext_code_hash {
+			disabled = disabled.with(Opcode::EXTCODEHASH);
+		}
+		if !self.has_base_fee {
+			disabled = disabled.with(Opcode::BASEFEE);
+		}
+		if !self.has_push0 {
+			disabled = disabled.with(Opcode::PUSH0);
+		}
+		if !self.has_mcopy {
+			disabled = disabled.with(Opcode::MCOPY);
+		}
+		if !self.has_transient_storage {
+			disabled = disabled.with(Opcode::TLOAD).with(Opcode::TSTORE);
+		}
+
+		disabled
+	}
+
 	/// Reference to default configuration
 	pub fn default() -> &'static Config {
 		&CONFIG
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{Capture, Config, Opcode, OpcodeSet, Runtime};
+	use crate::mock::{context, MockHandler};
+	use crate::{ExitError, ExitReason, ExitSucceed};
+
+	#[test]
+	fn test_presets() {
+		assert_eq!(Config::frontier().gas_transaction_create, 21000);
+		assert_eq!(Config::homestead().gas_transaction_create, 53000);
+		assert_eq!(Config::homestead().gas_call, 40);
+
+		let byzantium = Config::byzantium();
+		assert_eq!((byzantium.gas_call, byzantium.gas_sload, byzantium.gas_expbyte), (700, 200, 50));
+		assert!(byzantium.call_l64_after_gas && !byzantium.empty_considered_exists);
+		assert_eq!(byzantium.create_contract_limit, Some(0x6000));
+
+		let istanbul = Config::istanbul();
+		assert_eq!((istanbul.gas_sload, istanbul.gas_transaction_non_zero_data), (800, 16));
+		assert!(istanbul.sstore_gas_metering);
+
+		let berlin = Config::berlin();
+		assert_eq!((berlin.gas_sload_cold, berlin.gas_account_access_cold), (2100, 2600));
+		assert_eq!(berlin.gas_sstore_reset, 2900);
+		assert!(berlin.increase_state_access_gas);
+
+		let london = Config::london();
+		assert_eq!((london.refund_sstore_clears, london.max_refund_quotient), (4800, 5));
+
+		assert_eq!(Config::shanghai().max_initcode_size, Some(0xC000));
+		assert!(Config::shanghai().warm_coinbase_address);
+		assert!(Config::cancun().has_restricted_selfdestruct);
+		assert_eq!(Config::prague().total_cost_floor_per_token, 10);
+		assert_eq!(Config::osaka().max_transaction_gas_limit, Some(1 << 24));
+		assert_eq!(Config::osaka().gas_sload_cold, 2100);
+	}
+
+	#[test]
+	fn test_disabled_opcodes() {
+		let gated = [
+			(Config::frontier(), Opcode::DELEGATECALL),
+			(Config::homestead(), Opcode::REVERT),
+			(Config::byzantium(), Opcode::CREATE2),
+			(Config::constantinople(), Opcode::CHAINID),
+			(Config::istanbul(), Opcode::BASEFEE),
+			(Config::london(), Opcode::PUSH0),
+			(Config::shanghai(), Opcode::TSTORE),
+		];
+
+		// Each opcode is disabled up to the fork before the one that
+		// introduced it, and enabled from then on.
+		for (i, (config, opcode)) in gated.iter().enumerate() {
+			assert!(config.disabled_opcodes().contains(*opcode));
+			for (later, _) in &gated[i + 1..] {
+				assert!(!later.disabled_opcodes().contains(*opcode));
+			}
+		}
+		assert_eq!(Config::cancun().disabled_opcodes(), OpcodeSet::new());
+	}
+
+	#[test]
+	fn test_new_with_config() {
+		let config = Config { call_stack_limit: 7, ..Config::istanbul() };
+		let runtime = Runtime::new_with_config(Vec::new(), Vec::new(), Vec::new(), context(false), &config);
+		assert_eq!(runtime.config().call_stack_limit, 7);
+		assert_eq!(Runtime::new(Vec::new(), Vec::new(), Vec::new(), context(false)).config().call_stack_limit, 1024);
+
+		// The handler receives the config of the runtime, and the opcodes
+		// it disables are rejected.
+		let mut handler = MockHandler::default();
+		let code = vec![0x30, 0x50, 0x00];
+		assert_eq!(handler.run(code, context(false), &config), ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(handler.call_stack_limits, [7, 7, 7]);
+		let revert = vec![0x60, 0x00, 0x80, 0xfd];
+		let reason = handler.run(revert, context(false), &Config::homestead());
+		assert_eq!(reason, ExitError::InvalidCode(Opcode::REVERT.0).into());
+	}
+
+	#[test]
+	fn test_gated_opcodes() {
+		let push0 = vec![0x5f, 0x00];
+		let tload = vec![0x60, 0x00, 0x5c, 0x00];
+		let mcopy = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x5e, 0x00];
+		let run = |code: &Vec<u8>, config: &Config| MockHandler::default().run(code.clone(), context(false), config);
+
+		for (code, introduced_by, before) in [
+			(&push0, Config::shanghai(), Config::london()),
+			(&tload, Config::cancun(), Config::shanghai()),
+			(&mcopy, Config::cancun(), Config::shanghai()),
+		] {
+			assert_eq!(run(code, &before), ExitError::InvalidCode(code[code.len() - 2]).into());
+			assert_eq!(run(code, &introduced_by), ExitReason::Succeed(ExitSucceed::Stopped));
+			assert_eq!(run(code, &Config::osaka()), ExitReason::Succeed(ExitSucceed::Stopped));
+		}
+
+		// The default runtime still runs every implemented opcode.
+		let basefee = vec![0x48, 0x00];
+		for code in [push0, tload, mcopy, basefee] {
+			let mut runtime = Runtime::new(code, Vec::new(), Vec::new(), context(false));
+			let reason = runtime.run(u64::MAX, &mut MockHandler::default()).1;
+			assert!(matches!(reason, Capture::Exit(ExitReason::Succeed(ExitSucceed::Stopped))));
+		}
+	}
+}
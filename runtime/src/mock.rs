@@ -0,0 +1,141 @@
+//! Handler recording what the runtime asks of it, for tests.
+
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use crate::{
+	Capture, Config, Context, CreateScheme, ExitError, ExitFatal, ExitReason, ExitSucceed, Handler, Machine,
+	Opcode, Runtime, Stack, Transfer, H160, H256, U256,
+};
+
+/// Handler whose calls and creates return at once, and whose state changes
+/// are only counted.
+#[derive(Default)]
+pub struct MockHandler {
+	/// Gas left reported to the runtime.
+	pub gas_left: U256,
+	/// `call_stack_limit` of the config each `pre_validate` received.
+	pub call_stack_limits: Vec<usize>,
+	/// Target gas and static mode of each call.
+	pub calls: Vec<(Option<u64>, bool)>,
+	/// Target gas of each create.
+	pub creates: Vec<Option<u64>>,
+	/// Number of state changes: stores, logs and deletions.
+	pub changes: usize,
+}
+
+impl MockHandler {
+	/// Run `code` in `context` under `config` until it exits.
+	pub fn run(&mut self, code: Vec<u8>, context: Context, config: &Config) -> ExitReason {
+		let valids = evm_core::Valids::compute(&code);
+		let mut runtime = Runtime::new_with_config(code, valids, Vec::new(), context, config);
+		let reason = match runtime.run(u64::MAX, self).1 {
+			Capture::Exit(reason) => reason,
+			Capture::Trap(_) => unreachable!("calls and creates return at once"),
+		};
+		reason
+	}
+}
+
+impl Handler for MockHandler {
+	type CreateInterrupt = Infallible;
+	type CreateFeedback = Infallible;
+	type CallInterrupt = Infallible;
+	type CallFeedback = Infallible;
+
+	fn keccak256_h256(&self, _data: &[u8]) -> H256 { H256::zero() }
+	fn nonce(&self, _address: H160) -> U256 { U256::zero() }
+	fn exists(&self, _address: H160) -> bool { false }
+	fn balance(&self, _address: H160) -> U256 { U256::zero() }
+	fn code_size(&self, _address: H160) -> U256 { U256::zero() }
+	fn code_hash(&self, _address: H160) -> H256 { H256::zero() }
+	fn code(&self, _address: H160) -> Vec<u8> { Vec::new() }
+	fn valids(&self, _address: H160) -> Vec<u8> { Vec::new() }
+	fn storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+	fn original_storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+	fn transient_storage(&self, _address: H160, _index: U256) -> U256 { U256::zero() }
+	fn is_cold(&self, _address: H160, _index: Option<U256>) -> bool { false }
+	fn gas_left(&self) -> U256 { self.gas_left }
+	fn gas_price(&self) -> U256 { U256::zero() }
+	fn origin(&self) -> H160 { H160::zero() }
+	fn block_hash(&self, _number: U256) -> H256 { H256::zero() }
+	fn block_number(&self) -> U256 { U256::zero() }
+	fn block_coinbase(&self) -> H160 { H160::zero() }
+	fn block_timestamp(&self) -> U256 { U256::zero() }
+	fn block_difficulty(&self) -> U256 { U256::zero() }
+	fn block_gas_limit(&self) -> U256 { U256::zero() }
+	fn chain_id(&self) -> U256 { U256::zero() }
+
+	fn set_storage(&mut self, _address: H160, _index: U256, _value: U256) -> Result<(), ExitError> {
+		self.changes += 1;
+		Ok(())
+	}
+
+	fn set_transient_storage(&mut self, _address: H160, _index: U256, _value: U256) -> Result<(), ExitError> {
+		self.changes += 1;
+		Ok(())
+	}
+
+	fn log(&mut self, _address: H160, _topics: Vec<H256>, _data: Vec<u8>) -> Result<(), ExitError> {
+		self.changes += 1;
+		Ok(())
+	}
+
+	fn mark_delete(&mut self, _address: H160, _target: H160) -> Result<(), ExitError> {
+		self.changes += 1;
+		Ok(())
+	}
+
+	fn create(
+		&mut self,
+		_caller: H160,
+		_scheme: CreateScheme,
+		_value: U256,
+		_init_code: Vec<u8>,
+		target_gas: Option<u64>,
+		_depth: usize,
+		_config: &Config,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), Infallible> {
+		self.creates.push(target_gas);
+		Capture::Exit((ExitSucceed::Returned.into(), Some(H160::zero()), Vec::new()))
+	}
+
+	fn call(
+		&mut self,
+		_code_address: H160,
+		_transfer: Option<Transfer>,
+		_input: Vec<u8>,
+		target_gas: Option<u64>,
+		is_static: bool,
+		_context: Context,
+		_config: &Config,
+	) -> Capture<(ExitReason, Vec<u8>), Infallible> {
+		self.calls.push((target_gas, is_static));
+		Capture::Exit((ExitSucceed::Returned.into(), Vec::new()))
+	}
+
+	fn pre_validate(
+		&mut self,
+		_context: &Context,
+		_opcode: Opcode,
+		_stack: &Stack,
+		config: &Config,
+	) -> Result<(), ExitError> {
+		self.call_stack_limits.push(config.call_stack_limit);
+		Ok(())
+	}
+
+	fn other(&mut self, _opcode: Opcode, _machine: &mut Machine, _config: &Config) -> Result<(), ExitFatal> {
+		Err(ExitFatal::NotSupported)
+	}
+}
+
+/// Context of a call to the zero address.
+pub const fn context(is_static: bool) -> Context {
+	Context {
+		address: H160::zero(),
+		caller: H160::zero(),
+		apparent_value: U256::zero(),
+		depth: 0,
+		is_static,
+	}
+}
@@ -0,0 +1,131 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use crate::{H160, U256};
+
+/// Transient storage of a transaction (EIP-1153).
+///
+/// Writes are journaled per call frame like `Accessed`: `enter` opens a
+/// frame, `exit_revert` restores every slot written since, and
+/// `exit_commit` keeps the writes for the enclosing frame. Nothing survives
+/// the transaction: call `clear` once it ends.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct TransientStorage {
+	values: BTreeMap<(H160, U256), U256>,
+	journal: Vec<(H160, U256, U256)>,
+	checkpoints: Vec<usize>,
+}
+
+impl TransientStorage {
+	/// Create an empty transient storage.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			values: BTreeMap::new(),
+			journal: Vec::new(),
+			checkpoints: Vec::new(),
+		}
+	}
+
+	/// Value of a slot, zero if never written.
+	#[must_use]
+	pub fn get(&self, address: H160, index: U256) -> U256 {
+		self.values.get(&(address, index)).copied().unwrap_or_default()
+	}
+
+	/// Write a slot, journaling its previous value.
+	pub fn set(&mut self, address: H160, index: U256, value: U256) {
+		let previous = if value.is_zero() {
+			self.values.remove(&(address, index))
+		} else {
+			self.values.insert((address, index), value)
+		};
+		self.journal.push((address, index, previous.unwrap_or_default()));
+	}
+
+	/// Open a new call frame.
+	pub fn enter(&mut self) {
+		self.checkpoints.push(self.journal.len());
+	}
+
+	/// Close the current call frame, keeping its writes.
+	pub fn exit_commit(&mut self) {
+		self.checkpoints.pop();
+	}
+
+	/// Close the current call frame, undoing its writes.
+	pub fn exit_revert(&mut self) {
+		let checkpoint = self.checkpoints.pop().unwrap_or(0);
+
+		for (address, index, previous) in self.journal.drain(checkpoint..).rev() {
+			if previous.is_zero() {
+				self.values.remove(&(address, index));
+			} else {
+				self.values.insert((address, index), previous);
+			}
+		}
+	}
+
+	/// Drop every value and frame, at the end of a transaction.
+	pub fn clear(&mut self) {
+		self.values.clear();
+		self.journal.clear();
+		self.checkpoints.clear();
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::TransientStorage;
+	use crate::{H160, U256};
+
+	#[test]
+	fn test_exit_revert() {
+		let address = H160::repeat_byte(1);
+		let mut storage = TransientStorage::new();
+		storage.enter();
+		storage.set(address, U256::zero(), U256::one());
+
+		storage.enter();
+		storage.set(address, U256::zero(), U256::from(2));
+		storage.set(address, U256::one(), U256::from(3));
+		storage.exit_revert();
+
+		// Writes made before the frame are kept, those made in it undone.
+		assert_eq!(storage.get(address, U256::zero()), U256::one());
+		assert_eq!(storage.get(address, U256::one()), U256::zero());
+	}
+
+	#[test]
+	fn test_exit_commit() {
+		let address = H160::repeat_byte(1);
+		let mut storage = TransientStorage::new();
+
+		storage.enter();
+		storage.enter();
+		storage.set(address, U256::zero(), U256::one());
+		storage.exit_commit();
+		assert_eq!(storage.get(address, U256::zero()), U256::one());
+
+		// Committed writes belong to the parent, and revert with it.
+		storage.exit_revert();
+		assert_eq!(storage.get(address, U256::zero()), U256::zero());
+	}
+
+	#[test]
+	fn test_clear() {
+		let address = H160::repeat_byte(1);
+		let mut storage = TransientStorage::new();
+		storage.enter();
+		storage.enter();
+		storage.set(address, U256::zero(), U256::one());
+		storage.exit_commit();
+		storage.exit_commit();
+
+		// Nothing is left for the next transaction.
+		storage.clear();
+		assert_eq!(storage.get(address, U256::zero()), U256::zero());
+	}
+}
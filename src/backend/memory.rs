@@ -0,0 +1,217 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use crate::{H160, H256, U256};
+use super::{Apply, ApplyBackend, Backend, Basic, Log};
+
+/// Transaction and block environment of a `MemoryBackend`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryVicinity {
+	/// Gas price of the transaction.
+	pub gas_price: U256,
+	/// Origin of the transaction.
+	pub origin: H160,
+	/// Chain ID.
+	pub chain_id: U256,
+	/// Hashes of the most recent blocks, the parent block last.
+	pub block_hashes: Vec<H256>,
+	/// Current block number.
+	pub block_number: U256,
+	/// Current block coinbase.
+	pub block_coinbase: H160,
+	/// Current block timestamp.
+	pub block_timestamp: U256,
+	/// Current block difficulty.
+	pub block_difficulty: U256,
+	/// Current block gas limit.
+	pub block_gas_limit: U256,
+	/// Current block base fee per gas (EIP-1559).
+	pub block_base_fee_per_gas: U256,
+}
+
+/// An account of a `MemoryBackend`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryAccount {
+	/// Account nonce.
+	pub nonce: U256,
+	/// Account balance.
+	pub balance: U256,
+	/// Non-zero storage values.
+	pub storage: BTreeMap<U256, U256>,
+	/// Account code.
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
+	pub code: Vec<u8>,
+}
+
+impl MemoryAccount {
+	/// Whether the account has no nonce, balance or code (EIP-161).
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.nonce.is_zero() && self.balance.is_zero() && self.code.is_empty()
+	}
+}
+
+/// Backend keeping the whole world state in memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+	vicinity: MemoryVicinity,
+	state: BTreeMap<H160, MemoryAccount>,
+	logs: Vec<Log>,
+}
+
+impl MemoryBackend {
+	/// Create a backend with the given environment and accounts.
+	#[must_use]
+	pub const fn new(vicinity: MemoryVicinity, state: BTreeMap<H160, MemoryAccount>) -> Self {
+		Self { vicinity, state, logs: Vec::new() }
+	}
+
+	/// Transaction and block environment.
+	#[must_use]
+	pub const fn vicinity(&self) -> &MemoryVicinity {
+		&self.vicinity
+	}
+
+	/// Mutable transaction and block environment.
+	pub const fn vicinity_mut(&mut self) -> &mut MemoryVicinity {
+		&mut self.vicinity
+	}
+
+	/// Accounts of the world state.
+	#[must_use]
+	pub const fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
+		&self.state
+	}
+
+	/// Mutable accounts of the world state.
+	pub const fn state_mut(&mut self) -> &mut BTreeMap<H160, MemoryAccount> {
+		&mut self.state
+	}
+
+	/// Logs applied so far.
+	#[must_use]
+	pub fn logs(&self) -> &[Log] {
+		&self.logs
+	}
+}
+
+impl Backend for MemoryBackend {
+	fn gas_price(&self) -> U256 {
+		self.vicinity.gas_price
+	}
+
+	fn origin(&self) -> H160 {
+		self.vicinity.origin
+	}
+
+	fn block_hash(&self, number: U256) -> H256 {
+		if number >= self.vicinity.block_number {
+			return H256::zero()
+		}
+
+		let depth = self.vicinity.block_number - number - U256::one();
+		if depth >= U256::from(self.vicinity.block_hashes.len()) {
+			return H256::zero()
+		}
+
+		self.vicinity.block_hashes[self.vicinity.block_hashes.len() - 1 - depth.as_usize()]
+	}
+
+	fn block_number(&self) -> U256 {
+		self.vicinity.block_number
+	}
+
+	fn block_coinbase(&self) -> H160 {
+		self.vicinity.block_coinbase
+	}
+
+	fn block_timestamp(&self) -> U256 {
+		self.vicinity.block_timestamp
+	}
+
+	fn block_difficulty(&self) -> U256 {
+		self.vicinity.block_difficulty
+	}
+
+	fn block_gas_limit(&self) -> U256 {
+		self.vicinity.block_gas_limit
+	}
+
+	fn block_base_fee_per_gas(&self) -> U256 {
+		self.vicinity.block_base_fee_per_gas
+	}
+
+	fn chain_id(&self) -> U256 {
+		self.vicinity.chain_id
+	}
+
+	fn exists(&self, address: H160) -> bool {
+		self.state.contains_key(&address)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.state.get(&address).map_or_else(Basic::default, |account| Basic {
+			balance: account.balance,
+			nonce: account.nonce,
+		})
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.state.get(&address).map_or_else(Vec::new, |account| account.code.clone())
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.state
+			.get(&address)
+			.and_then(|account| account.storage.get(&index).copied())
+			.unwrap_or_default()
+	}
+
+	fn is_storage_empty(&self, address: H160) -> bool {
+		self.state.get(&address).is_none_or(|account| account.storage.is_empty())
+	}
+}
+
+impl ApplyBackend for MemoryBackend {
+	fn apply<A, I, L>(&mut self, values: A, logs: L, delete_empty: bool)
+	where
+		A: IntoIterator<Item = Apply<I>>,
+		I: IntoIterator<Item = (U256, U256)>,
+		L: IntoIterator<Item = Log>,
+	{
+		for apply in values {
+			match apply {
+				Apply::Modify { address, basic, code, storage, reset_storage } => {
+					let account = self.state.entry(address).or_default();
+					account.balance = basic.balance;
+					account.nonce = basic.nonce;
+					if let Some(code) = code {
+						account.code = code;
+					}
+
+					if reset_storage {
+						account.storage.clear();
+					}
+					for (index, value) in storage {
+						if value.is_zero() {
+							account.storage.remove(&index);
+						} else {
+							account.storage.insert(index, value);
+						}
+					}
+
+					if delete_empty && account.is_empty() {
+						self.state.remove(&address);
+					}
+				},
+				Apply::Delete { address } => {
+					self.state.remove(&address);
+				},
+			}
+		}
+
+		self.logs.extend(logs);
+	}
+}
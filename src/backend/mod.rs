@@ -0,0 +1,152 @@
+//! World and block state backends for the executor.
+
+mod memory;
+
+pub use self::memory::{MemoryAccount, MemoryBackend, MemoryVicinity};
+
+use alloc::vec::Vec;
+use crate::{H160, H256, U256};
+
+/// Balance and nonce of an account.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Basic {
+	/// Account balance.
+	pub balance: U256,
+	/// Account nonce.
+	pub nonce: U256,
+}
+
+/// A log emitted by a contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Log {
+	/// Address of the contract that emitted the log.
+	pub address: H160,
+	/// Log topics.
+	pub topics: Vec<H256>,
+	/// Log data.
+	#[cfg_attr(feature = "with-serde", serde(with = "serde_bytes"))]
+	pub data: Vec<u8>,
+}
+
+/// A change to an account, to be applied to a backend.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Apply<I> {
+	/// Modify or create an account.
+	Modify {
+		/// Address of the account.
+		address: H160,
+		/// New balance and nonce.
+		basic: Basic,
+		/// New code, if it was changed.
+		code: Option<Vec<u8>>,
+		/// Storage values written.
+		storage: I,
+		/// Whether the storage was cleared before `storage` was written.
+		reset_storage: bool,
+	},
+	/// Delete an account.
+	Delete {
+		/// Address of the account.
+		address: H160,
+	},
+}
+
+/// World and block state the executor reads from. Changes are never
+/// written back through it while executing, but can be applied afterwards
+/// with `ApplyBackend`.
+pub trait Backend {
+	/// Gas price of the transaction.
+	fn gas_price(&self) -> U256;
+	/// Origin of the transaction.
+	fn origin(&self) -> H160;
+	/// Hash of the block with the given number.
+	fn block_hash(&self, number: U256) -> H256;
+	/// Current block number.
+	fn block_number(&self) -> U256;
+	/// Current block coinbase.
+	fn block_coinbase(&self) -> H160;
+	/// Current block timestamp.
+	fn block_timestamp(&self) -> U256;
+	/// Current block difficulty.
+	fn block_difficulty(&self) -> U256;
+	/// Current block gas limit.
+	fn block_gas_limit(&self) -> U256;
+	/// Current block base fee per gas (EIP-1559).
+	fn block_base_fee_per_gas(&self) -> U256;
+	/// Chain ID.
+	fn chain_id(&self) -> U256;
+
+	/// Whether an account exists.
+	fn exists(&self, address: H160) -> bool;
+	/// Balance and nonce of an account.
+	fn basic(&self, address: H160) -> Basic;
+	/// Code of an account.
+	fn code(&self, address: H160) -> Vec<u8>;
+	/// Storage value of an account at index.
+	fn storage(&self, address: H160, index: U256) -> U256;
+	/// Whether all storage values of an account are zero (EIP-7610).
+	fn is_storage_empty(&self, address: H160) -> bool;
+}
+
+impl<B: Backend> Backend for &B {
+	fn gas_price(&self) -> U256 {
+		(*self).gas_price()
+	}
+	fn origin(&self) -> H160 {
+		(*self).origin()
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		(*self).block_hash(number)
+	}
+	fn block_number(&self) -> U256 {
+		(*self).block_number()
+	}
+	fn block_coinbase(&self) -> H160 {
+		(*self).block_coinbase()
+	}
+	fn block_timestamp(&self) -> U256 {
+		(*self).block_timestamp()
+	}
+	fn block_difficulty(&self) -> U256 {
+		(*self).block_difficulty()
+	}
+	fn block_gas_limit(&self) -> U256 {
+		(*self).block_gas_limit()
+	}
+	fn block_base_fee_per_gas(&self) -> U256 {
+		(*self).block_base_fee_per_gas()
+	}
+	fn chain_id(&self) -> U256 {
+		(*self).chain_id()
+	}
+	fn exists(&self, address: H160) -> bool {
+		(*self).exists(address)
+	}
+	fn basic(&self, address: H160) -> Basic {
+		(*self).basic(address)
+	}
+	fn code(&self, address: H160) -> Vec<u8> {
+		(*self).code(address)
+	}
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		(*self).storage(address, index)
+	}
+	fn is_storage_empty(&self, address: H160) -> bool {
+		(*self).is_storage_empty(address)
+	}
+}
+
+/// A backend that changes can be applied to.
+pub trait ApplyBackend {
+	/// Apply account changes and logs. With `delete_empty`, accounts left
+	/// empty (EIP-161) are deleted.
+	fn apply<A, I, L>(&mut self, values: A, logs: L, delete_empty: bool)
+	where
+		A: IntoIterator<Item = Apply<I>>,
+		I: IntoIterator<Item = (U256, U256)>,
+		L: IntoIterator<Item = Log>;
+}
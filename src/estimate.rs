@@ -0,0 +1,123 @@
+//! Gas estimation by binary search over the gas limit.
+
+use crate::ExitReason;
+
+/// Find the smallest gas limit in `[lower, upper]` with which `run` succeeds.
+///
+/// `run` executes the transaction against the handler with the given gas
+/// limit, and returns its exit reason together with the gas it used. Every
+/// attempt runs on a fresh clone of `handler`, so the state the estimation
+/// starts from is never modified.
+///
+/// The first attempt uses `upper` and must succeed, otherwise its exit reason
+/// is returned. The gas it used, measured with `Config::estimate` set, is
+/// usually the answer, so it is tried next together with the limit just
+/// below it, before searching whatever range is left.
+pub fn estimate_gas<H, F>(handler: &H, lower: u64, upper: u64, mut run: F) -> Result<u64, ExitReason>
+	where H: Clone, F: FnMut(&mut H, u64) -> (ExitReason, u64)
+{
+	let (reason, used_gas) = run(&mut handler.clone(), upper);
+	if !reason.is_succeed() {
+		return Err(reason)
+	}
+
+	let mut lower = lower;
+	let mut upper = upper;
+	let mut attempt = |gas_limit: u64, lower: &mut u64, upper: &mut u64| {
+		if run(&mut handler.clone(), gas_limit).0.is_succeed() {
+			*upper = gas_limit;
+		} else {
+			*lower = gas_limit + 1;
+		}
+	};
+
+	for gas_limit in [used_gas, used_gas.saturating_sub(1)] {
+		if (lower..upper).contains(&gas_limit) {
+			attempt(gas_limit, &mut lower, &mut upper);
+		}
+	}
+
+	while lower < upper {
+		attempt(lower + (upper - lower) / 2, &mut lower, &mut upper);
+	}
+
+	Ok(upper)
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{collections::BTreeMap, vec};
+	use super::estimate_gas;
+	use crate::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::executor::StackExecutor;
+	use crate::{Config, ExitError, ExitReason, ExitSucceed, Handler, H160, U256};
+
+	#[test]
+	fn test_estimate_gas() {
+		// Needs 50_000 gas up front, but reports 40_000 used after refunds.
+		let estimate = |lower, upper| {
+			let mut attempts = 0;
+			let estimate = estimate_gas(&(), lower, upper, |(), gas_limit| {
+				attempts += 1;
+				if gas_limit >= 50_000 {
+					(ExitReason::Succeed(ExitSucceed::Stopped), 40_000)
+				} else {
+					(ExitReason::Error(ExitError::OutOfGas), gas_limit)
+				}
+			});
+			(estimate, attempts)
+		};
+
+		assert_eq!(estimate(21_000, 1_000_000), (Ok(50_000), 22));
+		assert_eq!(estimate(45_000, 50_000), (Ok(50_000), 13));
+		assert_eq!(estimate(21_000, 30_000), (Err(ExitReason::Error(ExitError::OutOfGas)), 1));
+	}
+
+	#[test]
+	fn test_estimate_call() {
+		let outer = H160::repeat_byte(0xaa);
+		let inner = H160::repeat_byte(0xbb);
+
+		// SSTORE(0, 1), then STOP.
+		let inner_code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+		// CALL inner with all gas left, then STOP.
+		let mut outer_code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+		outer_code.extend_from_slice(inner.as_bytes());
+		outer_code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]);
+
+		let mut state = BTreeMap::new();
+		state.insert(outer, MemoryAccount { code: outer_code, ..MemoryAccount::default() });
+		state.insert(inner, MemoryAccount { code: inner_code, ..MemoryAccount::default() });
+		let backend = MemoryBackend::new(MemoryVicinity::default(), state);
+
+		let estimate_config = Config { estimate: true, ..Config::cancun() };
+		let config = Config::cancun();
+		let run = |config: &Config, backend: &MemoryBackend, gas_limit| {
+			let mut executor = StackExecutor::new(backend.clone(), config);
+			executor.enter_call(H160::zero(), outer, U256::zero(), vec![], gas_limit);
+			let reason = executor.execute(u64::MAX).1;
+			// The transaction succeeds even if the call fails.
+			let reason = if executor.storage(inner, U256::zero()).is_zero() {
+				ExitReason::Error(ExitError::OutOfGas)
+			} else {
+				reason
+			};
+			(reason, executor.used_gas())
+		};
+
+		let mut attempts = 0;
+		let estimate = estimate_gas(&backend, 21_000, 1_000_000, |backend, gas_limit| {
+			attempts += 1;
+			run(&estimate_config, backend, gas_limit)
+		});
+
+		// The gas measured in estimate mode is the smallest gas limit that
+		// works, so the search only confirms it.
+		let (_, used_gas) = run(&estimate_config, &backend, 1_000_000);
+		assert_eq!(estimate, Ok(used_gas));
+		assert!(run(&config, &backend, used_gas).0.is_succeed());
+		assert!(!run(&config, &backend, used_gas - 1).0.is_succeed());
+		assert!(run(&config, &backend, used_gas).1 < used_gas);
+		assert_eq!(attempts, 3);
+	}
+}
@@ -0,0 +1,8 @@
+//! Executors that drive the EVM runtime over a world state.
+
+mod stack;
+
+pub use self::stack::{
+	transact_call, transact_create, StackAccount, StackExecutor, StackFrame, StackSubstate, TransactError,
+	TransactOutcome, Transaction,
+};
@@ -0,0 +1,989 @@
+//! Executor running nested call frames on a stack of runtimes, with the
+//! changes of every frame journaled in a `StackSubstate`.
+
+mod substate;
+mod transact;
+
+pub use self::substate::{StackAccount, StackSubstate};
+pub use self::transact::{transact_call, transact_create, TransactError, TransactOutcome, Transaction};
+
+use alloc::vec::Vec;
+use core::cmp::min;
+use sha3::{Digest, Keccak256};
+use crate::backend::{Backend, Basic};
+use crate::precompile::{PrecompileFailure, PrecompileInput, PrecompileOutput, PrecompileResult, PrecompileSet};
+use crate::receipt::Receipt;
+use crate::gasometer::{Gasometer, StorageTarget, TransactionCost};
+use crate::{
+	save_created_address, save_return_value, Accessed, Capture, Config, Context, Control, CreateScheme, ExitError,
+	ExitFatal, ExitReason, Handler, Machine, Opcode, Resolve, Runtime, Stack, Transfer, TransientStorage, Valids, H160, H256, U256,
+};
+
+/// What a frame was entered for.
+#[derive(Clone, Copy, Debug)]
+enum FrameKind {
+	Call,
+	Create(H160),
+}
+
+/// How the changes of an exiting frame are kept.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum StackExitKind {
+	/// Changes are committed, unused gas and refunds go back to the parent.
+	Succeeded,
+	/// Changes are discarded, unused gas goes back to the parent.
+	Reverted,
+	/// Changes are discarded, and so is the gas of the frame.
+	Failed,
+}
+
+impl From<&ExitReason> for StackExitKind {
+	fn from(reason: &ExitReason) -> Self {
+		match reason {
+			ExitReason::Succeed(_) => Self::Succeeded,
+			ExitReason::Revert(_) => Self::Reverted,
+			_ => Self::Failed,
+		}
+	}
+}
+
+/// A call frame waiting to be run by a `StackExecutor`.
+pub struct StackFrame {
+	runtime: Runtime,
+	kind: FrameKind,
+}
+
+/// Executor of a message call or contract creation over a `Backend`.
+///
+/// Sub-calls and creates do not recurse: `Handler::call` and
+/// `Handler::create` hand back a `StackFrame`, which `execute` pushes on top
+/// of its caller and runs until it exits. Each frame has its own gasometer,
+/// and opens a substate that is committed or reverted according to how the
+/// frame exits. Calls to an address of the `PrecompileSet` run the
+/// precompile instead, without a frame.
+pub struct StackExecutor<'config, B, P = ()> {
+	config: &'config Config,
+	backend: B,
+	precompiles: &'config P,
+	substate: StackSubstate,
+	accessed: Accessed,
+	transient_storage: TransientStorage,
+	gasometers: Vec<Gasometer<'config>>,
+	frames: Vec<StackFrame>,
+	exit: Option<(ExitReason, Option<H160>, Vec<u8>)>,
+	used_gas: u64,
+	transaction_cost: Option<TransactionCost>,
+	origin: Option<H160>,
+	gas_price: Option<U256>,
+}
+
+impl<'config, B: Backend> StackExecutor<'config, B> {
+	/// Create an executor over `backend`, without precompiles.
+	pub const fn new(backend: B, config: &'config Config) -> Self {
+		Self::new_with_precompiles(backend, config, &())
+	}
+}
+
+impl<'config, B: Backend, P: PrecompileSet> StackExecutor<'config, B, P> {
+	/// Create an executor over `backend`, with the given precompiles.
+	pub const fn new_with_precompiles(backend: B, config: &'config Config, precompiles: &'config P) -> Self {
+		Self {
+			config,
+			backend,
+			precompiles,
+			substate: StackSubstate::new(),
+			accessed: Accessed::new(),
+			transient_storage: TransientStorage::new(),
+			gasometers: Vec::new(),
+			frames: Vec::new(),
+			exit: None,
+			used_gas: 0,
+			transaction_cost: None,
+			origin: None,
+			gas_price: None,
+		}
+	}
+
+	/// Set the origin and effective gas price of the transaction, instead of
+	/// reading them from the backend.
+	pub const fn set_transaction_env(&mut self, origin: H160, gas_price: U256) {
+		self.origin = Some(origin);
+		self.gas_price = Some(gas_price);
+	}
+
+	/// Configuration of the executor.
+	pub const fn config(&self) -> &'config Config {
+		self.config
+	}
+
+	/// Backend the executor reads from.
+	pub const fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	/// Changes made so far.
+	pub const fn substate(&self) -> &StackSubstate {
+		&self.substate
+	}
+
+	/// Mutable access set, to pre-warm addresses and slots before entering.
+	pub const fn accessed_mut(&mut self) -> &mut Accessed {
+		&mut self.accessed
+	}
+
+	/// Gas used by the outermost frame once it has exited, after refunds.
+	pub const fn used_gas(&self) -> u64 {
+		self.used_gas
+	}
+
+	/// Data returned by the outermost frame once it has exited.
+	#[must_use]
+	pub fn return_value(&self) -> &[u8] {
+		self.exit.as_ref().map_or(&[], |(_, _, data)| data)
+	}
+
+	/// Address created by the outermost frame, if it was a successful create.
+	pub fn created_address(&self) -> Option<H160> {
+		self.exit.as_ref().and_then(|(_, address, _)| *address)
+	}
+
+	/// Receipt of the outermost frame once it has exited, given the gas used
+	/// by the block before this transaction. Logs of reverted frames are
+	/// left out.
+	#[must_use]
+	pub fn receipt(&self, cumulative_gas_used: u64) -> Receipt {
+		let status = self.exit.as_ref().is_some_and(|(reason, _, _)| reason.is_succeed());
+		Receipt::new(status, cumulative_gas_used.saturating_add(self.used_gas), self.substate.logs().to_vec())
+	}
+
+	/// Consume the executor, returning the backend and the changes made.
+	pub fn into_parts(self) -> (B, StackSubstate) {
+		(self.backend, self.substate)
+	}
+
+	/// Enter a message call from `caller` to `address` as the outermost
+	/// frame, to be run by `execute`.
+	pub fn enter_call(&mut self, caller: H160, address: H160, value: U256, data: Vec<u8>, gas_limit: u64) {
+		let gasometer = match self.outermost_gasometer(gas_limit) {
+			Ok(gasometer) => gasometer,
+			Err(error) => return self.fail_outermost(error, gas_limit),
+		};
+		self.access_precompiles();
+		self.accessed.access_address(caller);
+		self.accessed.access_address(address);
+
+		let context = Context { address, caller, apparent_value: value, depth: 0, is_static: false };
+		let transfer = Transfer { source: caller, target: address, value };
+		match self.call_frame(address, Some(transfer), data, gasometer, context) {
+			Ok(frame) => self.frames.push(frame),
+			Err((reason, data)) => self.exit = Some((reason, None, data)),
+		}
+	}
+
+	/// Enter a contract creation by `caller` as the outermost frame, to be
+	/// run by `execute`.
+	pub fn enter_create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+	) {
+		let gasometer = match self.outermost_gasometer(gas_limit) {
+			Ok(gasometer) => gasometer,
+			Err(error) => return self.fail_outermost(error, gas_limit),
+		};
+		self.access_precompiles();
+		self.accessed.access_address(caller);
+
+		if let Err(error) = self.check_create(caller, value, 0) {
+			self.exit = Some((error.into(), None, Vec::new()));
+			return
+		}
+		match self.create_frame(caller, scheme, value, init_code, gasometer, 0) {
+			Ok(frame) => self.frames.push(frame),
+			Err(reason) => self.exit = Some((reason, None, Vec::new())),
+		}
+	}
+
+	/// Run the entered frames for at most `max_steps` steps. Returns the
+	/// steps executed, and the exit reason of the outermost frame, or
+	/// `StepLimitReached` if it has not exited yet, in which case `execute`
+	/// can be called again to resume.
+	pub fn execute(&mut self, max_steps: u64) -> (u64, ExitReason) {
+		let mut steps = 0_u64;
+
+		while steps < max_steps {
+			let Some(mut frame) = self.frames.pop() else { break };
+
+			let (executed, trapped) = {
+				let (executed, capture) = frame.runtime.run(max_steps - steps, self);
+				let trapped = match capture {
+					Capture::Exit(reason) => Err(reason),
+					Capture::Trap(Resolve::Call(child, resolve)) => {
+						resolve.release();
+						Ok(child)
+					},
+					Capture::Trap(Resolve::Create(child, resolve)) => {
+						resolve.release();
+						Ok(child)
+					},
+				};
+				(executed, trapped)
+			};
+			steps = steps.saturating_add(executed);
+
+			match trapped {
+				Err(ExitReason::StepLimitReached) => {
+					self.frames.push(frame);
+					break
+				},
+				Err(reason) => {
+					let data = frame.runtime.machine().return_value();
+					self.exit_frame(frame.kind, reason, data);
+				},
+				Ok(child) => {
+					self.frames.push(frame);
+					self.frames.push(child);
+				},
+			}
+		}
+
+		match &self.exit {
+			Some((reason, _, _)) if self.frames.is_empty() => (steps, *reason),
+			_ => (steps, ExitReason::StepLimitReached),
+		}
+	}
+
+	/// Warm the precompile addresses (EIP-2929).
+	fn access_precompiles(&mut self) {
+		for address in self.precompiles.addresses() {
+			self.accessed.access_address(address);
+		}
+	}
+
+	fn gasometer_mut(&mut self) -> &mut Gasometer<'config> {
+		self.gasometers.last_mut().expect("a frame is running")
+	}
+
+	fn runtime(&self, code: Vec<u8>, data: Vec<u8>, context: Context) -> Runtime {
+		let valids = Valids::compute(&code);
+		Runtime::new_with_config(code, valids, data, context, self.config)
+	}
+
+	/// Gasometer of the outermost frame, charged the intrinsic gas of the
+	/// transaction if there is one.
+	fn outermost_gasometer(&mut self, gas_limit: u64) -> Result<Gasometer<'config>, ExitError> {
+		let mut gasometer = Gasometer::new(gas_limit, self.config);
+		if let Some(cost) = self.transaction_cost.take() {
+			gasometer.record_transaction(cost)?;
+		}
+		Ok(gasometer)
+	}
+
+	/// Exit before entering the outermost frame, consuming all its gas.
+	fn fail_outermost(&mut self, error: ExitError, gas_limit: u64) {
+		self.used_gas = gas_limit;
+		self.exit = Some((error.into(), None, Vec::new()));
+	}
+
+	fn enter_substate(&mut self, gasometer: Gasometer<'config>) {
+		self.gasometers.push(gasometer);
+		self.substate.enter();
+		self.accessed.enter();
+		self.transient_storage.enter();
+	}
+
+	fn exit_substate(&mut self, kind: StackExitKind) {
+		let gasometer = self.gasometers.pop().expect("exiting frame has a gasometer");
+
+		if kind == StackExitKind::Succeeded {
+			self.substate.exit_commit();
+			self.accessed.exit_commit();
+			self.transient_storage.exit_commit();
+		} else {
+			self.substate.exit_revert();
+			self.accessed.exit_revert();
+			self.transient_storage.exit_revert();
+		}
+
+		if let Some(parent) = self.gasometers.last_mut() {
+			match kind {
+				StackExitKind::Succeeded => {
+					let _ = parent.record_stipend(gasometer.gas());
+					let _ = parent.record_refund(gasometer.refunded_gas());
+					let _ = parent.record_call_reserve(&gasometer);
+				},
+				StackExitKind::Reverted => {
+					let _ = parent.record_stipend(gasometer.gas());
+					let _ = parent.record_call_reserve(&gasometer);
+				},
+				StackExitKind::Failed => (),
+			}
+		} else {
+			self.transient_storage.clear();
+			self.used_gas = match kind {
+				StackExitKind::Succeeded => gasometer.effective_used_gas(),
+				StackExitKind::Reverted => gasometer.reverted_used_gas(),
+				StackExitKind::Failed => gasometer.gas_limit(),
+			};
+		}
+	}
+
+	/// Pay for a sub-frame out of the current one, returning its gas limit.
+	fn charge_sub_frame(&mut self, target_gas: Option<u64>, stipend: u64) -> Result<u64, ExitError> {
+		let gasometer = self.gasometer_mut();
+		let gas = min(target_gas.unwrap_or(u64::MAX).saturating_sub(stipend), gasometer.gas());
+		gasometer.record_cost(gas)?;
+
+		Ok(gas.saturating_add(stipend))
+	}
+
+	fn call_frame(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		gasometer: Gasometer<'config>,
+		context: Context,
+	) -> Result<StackFrame, (ExitReason, Vec<u8>)> {
+		self.enter_substate(gasometer);
+		self.substate.touch(context.address);
+
+		if let Some(transfer) = transfer {
+			if let Err(error) = self.substate.transfer(&transfer, &self.backend) {
+				self.exit_substate(StackExitKind::Reverted);
+				return Err((error.into(), Vec::new()))
+			}
+		}
+
+		let gas_limit = self.gasometer_mut().gas();
+		let precompile_input = PrecompileInput { input: &input, gas_limit, context: &context, config: self.config };
+		if let Some(result) = self.precompiles.execute(code_address, &precompile_input) {
+			return Err(self.exit_precompile(result))
+		}
+
+		let code = self.code(code_address);
+		Ok(StackFrame { runtime: self.runtime(code, input, context), kind: FrameKind::Call })
+	}
+
+	/// Charge the cost of a precompile call and exit its substate, returning
+	/// the exit reason and output.
+	fn exit_precompile(&mut self, result: PrecompileResult) -> (ExitReason, Vec<u8>) {
+		let (reason, cost, output) = match result {
+			Ok(PrecompileOutput { exit_status, cost, output }) => (exit_status.into(), cost, output),
+			Err(PrecompileFailure::Revert { exit_status, cost, output }) => (exit_status.into(), cost, output),
+			Err(failure) => (failure.exit_reason(), 0, Vec::new()),
+		};
+
+		let (reason, output) = match self.gasometer_mut().record_cost(cost) {
+			Ok(()) => (reason, output),
+			Err(error) => (error.into(), Vec::new()),
+		};
+		self.exit_substate(StackExitKind::from(&reason));
+		(reason, output)
+	}
+
+	/// Fail a create that must leave the caller untouched: one beyond the
+	/// call stack limit, lacking the value, or overflowing the caller nonce.
+	fn check_create(&self, caller: H160, value: U256, depth: usize) -> Result<(), ExitError> {
+		if depth > self.config.call_stack_limit {
+			return Err(ExitError::CallTooDeep)
+		}
+		if self.balance(caller) < value {
+			return Err(ExitError::OutOfFund)
+		}
+		if self.nonce(caller) >= U256::from(u64::MAX) {
+			return Err(ExitError::MaxNonce)
+		}
+		Ok(())
+	}
+
+	/// Bump the caller nonce and enter the frame of a create that passed
+	/// `check_create`.
+	fn create_frame(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		value: U256,
+		init_code: Vec<u8>,
+		gasometer: Gasometer<'config>,
+		depth: usize,
+	) -> Result<StackFrame, ExitReason> {
+		let address = scheme.address(self.nonce(caller), keccak256);
+		self.substate.inc_nonce(caller, &self.backend);
+		self.accessed.access_address(address);
+
+		self.enter_substate(gasometer);
+		if self.is_collision(address) {
+			self.exit_substate(StackExitKind::Failed);
+			return Err(ExitError::CreateCollision.into())
+		}
+
+		self.substate.set_created(address);
+		self.substate.touch(address);
+		if self.config.create_increase_nonce {
+			self.substate.inc_nonce(address, &self.backend);
+		}
+		self.substate.reset_storage(address, &self.backend);
+
+		let transfer = Transfer { source: caller, target: address, value };
+		if let Err(error) = self.substate.transfer(&transfer, &self.backend) {
+			self.exit_substate(StackExitKind::Reverted);
+			return Err(error.into())
+		}
+
+		let context = Context { address, caller, apparent_value: value, depth, is_static: false };
+		Ok(StackFrame { runtime: self.runtime(init_code, Vec::new(), context), kind: FrameKind::Create(address) })
+	}
+
+	/// Whether an account already exists at a create address (EIP-684),
+	/// including one with only storage (EIP-7610).
+	fn is_collision(&self, address: H160) -> bool {
+		!self.nonce(address).is_zero()
+			|| !self.code(address).is_empty()
+			|| (self.substate.known_original_storage(address).is_none() && !self.backend.is_storage_empty(address))
+	}
+
+	/// Deposit the code returned by a create, enforcing the code size limit
+	/// (EIP-170), the 0xEF prefix rejection (EIP-3541) and the per-byte cost.
+	fn deposit_code(&mut self, address: H160, code: Vec<u8>) -> Result<(), ExitError> {
+		if self.config.create_contract_limit.is_some_and(|limit| code.len() > limit) {
+			return Err(ExitError::CreateContractLimit)
+		}
+		if self.config.disallow_executable_format && code.first() == Some(&0xef) {
+			return Err(ExitError::CreateContractStartingWithEF)
+		}
+
+		self.gasometer_mut().record_deposit(code.len())?;
+		self.substate.set_code(address, code, &self.backend);
+		Ok(())
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.substate.known_basic(address).unwrap_or_else(|| self.backend.basic(address))
+	}
+
+	/// Exit the frame on top of the stack, resuming its parent with the
+	/// result. Exits cascade as long as resuming makes the parent exit too.
+	fn exit_frame(&mut self, kind: FrameKind, reason: ExitReason, data: Vec<u8>) {
+		let (mut kind, mut reason, mut data) = (kind, reason, data);
+
+		loop {
+			let (return_data, address) = match kind {
+				FrameKind::Create(address) if reason.is_succeed() => match self.deposit_code(address, data) {
+					Ok(()) => (Vec::new(), Some(address)),
+					Err(error) => {
+						reason = error.into();
+						(Vec::new(), None)
+					},
+				},
+				_ => (data, None),
+			};
+			self.exit_substate(StackExitKind::from(&reason));
+
+			let Some(parent) = self.frames.last_mut() else {
+				self.exit = Some((reason, address, return_data));
+				return
+			};
+
+			let control = match kind {
+				FrameKind::Call => save_return_value::<Self>(&mut parent.runtime, reason, return_data),
+				FrameKind::Create(_) => {
+					parent.runtime.set_return_data(return_data);
+					save_created_address::<Self>(&mut parent.runtime, reason, address)
+				},
+			};
+
+			let Control::Exit(exit) = control else { return };
+			let parent = self.frames.pop().expect("parent frame was just resumed");
+			kind = parent.kind;
+			reason = exit;
+			data = Vec::new();
+		}
+	}
+}
+
+fn keccak256(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+impl<B: Backend, P: PrecompileSet> Handler for StackExecutor<'_, B, P> {
+	type CreateInterrupt = StackFrame;
+	type CreateFeedback = ();
+	type CallInterrupt = StackFrame;
+	type CallFeedback = ();
+
+	fn keccak256_h256(&self, data: &[u8]) -> H256 {
+		keccak256(data)
+	}
+
+	fn nonce(&self, address: H160) -> U256 {
+		self.basic(address).nonce
+	}
+
+	fn exists(&self, address: H160) -> bool {
+		self.substate.known_account(address).is_some() || self.backend.exists(address)
+	}
+
+	fn balance(&self, address: H160) -> U256 {
+		self.basic(address).balance
+	}
+
+	fn code_size(&self, address: H160) -> U256 {
+		U256::from(self.code(address).len())
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		let basic = self.basic(address);
+		let code = self.code(address);
+
+		// Non-existent and empty accounts have no code hash (EIP-1052, EIP-161).
+		if !self.exists(address) || (basic.nonce.is_zero() && basic.balance.is_zero() && code.is_empty()) {
+			return H256::zero()
+		}
+
+		keccak256(&code)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.substate.known_code(address).unwrap_or_else(|| self.backend.code(address))
+	}
+
+	fn valids(&self, address: H160) -> Vec<u8> {
+		Valids::compute(&self.code(address))
+	}
+
+	fn storage(&self, address: H160, index: U256) -> U256 {
+		self.substate.known_storage(address, index).unwrap_or_else(|| self.backend.storage(address, index))
+	}
+
+	fn original_storage(&self, address: H160, index: U256) -> U256 {
+		self.substate.known_original_storage(address).unwrap_or_else(|| self.backend.storage(address, index))
+	}
+
+	fn transient_storage(&self, address: H160, index: U256) -> U256 {
+		self.transient_storage.get(address, index)
+	}
+
+	fn is_cold(&self, address: H160, index: Option<U256>) -> bool {
+		self.accessed.is_cold(address, index)
+	}
+
+	fn gas_left(&self) -> U256 {
+		U256::from(self.gasometers.last().map_or(0, Gasometer::gas))
+	}
+
+	fn gas_price(&self) -> U256 {
+		self.gas_price.unwrap_or_else(|| self.backend.gas_price())
+	}
+
+	fn origin(&self) -> H160 {
+		self.origin.unwrap_or_else(|| self.backend.origin())
+	}
+
+	fn block_hash(&self, number: U256) -> H256 {
+		self.backend.block_hash(number)
+	}
+
+	fn block_number(&self) -> U256 {
+		self.backend.block_number()
+	}
+
+	fn block_coinbase(&self) -> H160 {
+		self.backend.block_coinbase()
+	}
+
+	fn block_timestamp(&self) -> U256 {
+		self.backend.block_timestamp()
+	}
+
+	fn block_difficulty(&self) -> U256 {
+		self.backend.block_difficulty()
+	}
+
+	fn block_gas_limit(&self) -> U256 {
+		self.backend.block_gas_limit()
+	}
+
+	fn chain_id(&self) -> U256 {
+		self.backend.chain_id()
+	}
+
+	fn set_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		self.substate.set_storage(address, index, value);
+		Ok(())
+	}
+
+	fn set_transient_storage(&mut self, address: H160, index: U256, value: U256) -> Result<(), ExitError> {
+		self.transient_storage.set(address, index, value);
+		Ok(())
+	}
+
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		self.substate.log(address, topics, data);
+		Ok(())
+	}
+
+	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
+		let value = self.balance(address);
+		self.substate.transfer(&Transfer { source: address, target, value }, &self.backend)?;
+
+		if self.config.has_restricted_selfdestruct && !self.substate.created(address) {
+			return Ok(())
+		}
+
+		if !self.substate.deleted(address) {
+			let refund = self.config.refund_suicide;
+			self.gasometer_mut().record_refund(refund)?;
+		}
+		self.substate.reset_balance(address, &self.backend);
+		self.substate.set_deleted(address);
+		Ok(())
+	}
+
+	fn create(
+		&mut self,
+		caller: H160,
+		scheme: CreateScheme,
+		value: U256,
+		init_code: Vec<u8>,
+		target_gas: Option<u64>,
+		depth: usize,
+		_config: &Config,
+	) -> Capture<(ExitReason, Option<H160>, Vec<u8>), StackFrame> {
+		if let Err(error) = self.check_create(caller, value, depth) {
+			return Capture::Exit((error.into(), None, Vec::new()))
+		}
+		let gas_limit = match self.charge_sub_frame(target_gas, 0) {
+			Ok(gas_limit) => gas_limit,
+			Err(error) => return Capture::Exit((error.into(), None, Vec::new())),
+		};
+
+		let gasometer = Gasometer::new(gas_limit, self.config);
+		match self.create_frame(caller, scheme, value, init_code, gasometer, depth) {
+			Ok(frame) => Capture::Trap(frame),
+			Err(reason) => Capture::Exit((reason, None, Vec::new())),
+		}
+	}
+
+	fn call(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		target_gas: Option<u64>,
+		_is_static: bool,
+		context: Context,
+		_config: &Config,
+	) -> Capture<(ExitReason, Vec<u8>), StackFrame> {
+		let stipend = if transfer.as_ref().is_some_and(|transfer| !transfer.value.is_zero()) {
+			self.config.call_stipend
+		} else {
+			0
+		};
+
+		let gas_limit = match self.charge_sub_frame(target_gas, stipend) {
+			Ok(gas_limit) => gas_limit,
+			Err(error) => return Capture::Exit((error.into(), Vec::new())),
+		};
+
+		let gasometer = Gasometer::new(gas_limit, self.config);
+		match self.call_frame(code_address, transfer, input, gasometer, context) {
+			Ok(frame) => Capture::Trap(frame),
+			Err(exit) => Capture::Exit(exit),
+		}
+	}
+
+	fn pre_validate(
+		&mut self,
+		context: &Context,
+		opcode: Opcode,
+		stack: &Stack,
+		_config: &Config,
+	) -> Result<(), ExitError> {
+		// The gasometer leaves the stack while it prices the opcode, as the
+		// executor is the handler answering its queries.
+		let mut gasometer = self.gasometers.pop().expect("a frame is running");
+		let target = gasometer.record_opcode(context.address, opcode, stack, self);
+		self.gasometers.push(gasometer);
+
+		match target? {
+			StorageTarget::Address(address) => self.accessed.access_address(address),
+			StorageTarget::Slot(address, index) => self.accessed.access_storage(address, index),
+			StorageTarget::None => (),
+		}
+
+		Ok(())
+	}
+
+	fn other(&mut self, opcode: Opcode, machine: &mut Machine, _config: &Config) -> Result<(), ExitFatal> {
+		machine.exit(ExitError::InvalidCode(opcode.0).into());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{collections::BTreeMap, vec, vec::Vec};
+	use core::str::FromStr;
+	use super::StackExecutor;
+	use crate::backend::{ApplyBackend, Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::precompile::{PrecompileFn, PrecompileInput, PrecompileOutput, PrecompileResult};
+	use crate::{Config, CreateScheme, ExitError, ExitReason, ExitSucceed, Handler, H160, H256, U256};
+
+	/// State with an account holding the given code at each address.
+	fn state(contracts: Vec<(H160, Vec<u8>)>) -> BTreeMap<H160, MemoryAccount> {
+		contracts
+			.into_iter()
+			.map(|(address, code)| (address, MemoryAccount { code, ..MemoryAccount::default() }))
+			.collect()
+	}
+
+	/// Executor over a backend holding `state`.
+	fn executor(config: &Config, state: BTreeMap<H160, MemoryAccount>) -> StackExecutor<'_, MemoryBackend> {
+		StackExecutor::new(MemoryBackend::new(MemoryVicinity::default(), state), config)
+	}
+
+	/// Call `address` from the zero address over `state`, and run the call
+	/// to completion.
+	fn call(
+		config: &Config,
+		state: BTreeMap<H160, MemoryAccount>,
+		address: H160,
+		gas_limit: u64,
+	) -> (StackExecutor<'_, MemoryBackend>, ExitReason) {
+		let mut executor = executor(config, state);
+		executor.enter_call(H160::zero(), address, U256::zero(), Vec::new(), gas_limit);
+		let reason = executor.execute(u64::MAX).1;
+		(executor, reason)
+	}
+
+	/// Apply the changes made by `executor` to its backend.
+	fn commit(executor: StackExecutor<'_, MemoryBackend>) -> MemoryBackend {
+		let (mut backend, substate) = executor.into_parts();
+		let (applies, logs) = substate.deconstruct(&backend);
+		backend.apply(applies, logs, true);
+		backend
+	}
+
+	#[test]
+	fn test_reverted_call_frame() {
+		let outer = H160::repeat_byte(0xaa);
+		let inner = H160::repeat_byte(0xbb);
+
+		// SSTORE(0, 2), then REVERT.
+		let inner_code = vec![0x60, 0x02, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd];
+		// SSTORE(0, 1), then CALL inner with all gas left, then STOP.
+		let mut outer_code = vec![
+			0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00,
+		];
+		outer_code.push(0x73);
+		outer_code.extend_from_slice(inner.as_bytes());
+		outer_code.extend_from_slice(&[0x5a, 0xf1, 0x00]);
+
+		let config = Config::cancun();
+		let state = state(vec![(outer, outer_code), (inner, inner_code)]);
+		let (executor, reason) = call(&config, state, outer, 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert!(executor.used_gas() > 0);
+
+		let backend = commit(executor);
+		assert_eq!(backend.storage(outer, U256::zero()), U256::one());
+		assert_eq!(backend.storage(inner, U256::zero()), U256::zero());
+		assert!(!backend.exists(H160::zero()));
+	}
+
+	#[test]
+	fn test_create_starting_with_ef() {
+		let caller = H160::repeat_byte(0xaa);
+		// MSTORE8(0, 0xef), then RETURN(0, 1).
+		let init_code = vec![0x60, 0xef, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+		let scheme = CreateScheme::Legacy { caller };
+
+		let config = Config::berlin();
+		let mut executor = StackExecutor::new(MemoryBackend::default(), &config);
+		executor.enter_create(caller, scheme, U256::zero(), init_code.clone(), 100_000);
+		assert_eq!(executor.execute(u64::MAX).1, ExitReason::Succeed(ExitSucceed::Returned));
+		let address = executor.created_address().unwrap();
+		assert_eq!(executor.code(address), vec![0xef]);
+
+		let config = Config::london();
+		let mut executor = StackExecutor::new(MemoryBackend::default(), &config);
+		executor.enter_create(caller, scheme, U256::zero(), init_code, 100_000);
+		assert_eq!(
+			executor.execute(u64::MAX).1,
+			ExitReason::Error(ExitError::CreateContractStartingWithEF),
+		);
+		assert_eq!(executor.used_gas(), 100_000);
+		assert_eq!(executor.created_address(), None);
+	}
+
+	#[test]
+	fn test_create_insufficient_balance() {
+		let caller = H160::repeat_byte(0xaa);
+		// SSTORE(0, CREATE(1, 0, 0)), then STOP.
+		let code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x01, 0xf0, 0x60, 0x00, 0x55, 0x00];
+		let address = CreateScheme::Legacy { caller }.address(U256::zero(), super::keccak256);
+
+		let config = Config::cancun();
+		let (executor, reason) = call(&config, state(vec![(caller, code.clone())]), caller, 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(executor.storage(caller, U256::zero()), U256::zero());
+		assert_eq!(executor.nonce(caller), U256::zero());
+		assert!(executor.is_cold(address, None));
+		let used_gas = executor.used_gas();
+
+		let mut state = state(vec![(caller, code)]);
+		state.get_mut(&caller).unwrap().balance = U256::one();
+		let (executor, reason) = call(&config, state, caller, 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(executor.storage(caller, U256::zero()), U256::from(address.as_bytes()));
+		assert_eq!(executor.nonce(caller), U256::one());
+		assert!(!executor.is_cold(address, None));
+		assert!(executor.used_gas() > used_gas);
+	}
+
+	#[test]
+	fn test_call_too_deep() {
+		let address = H160::repeat_byte(0xaa);
+		// SSTORE(0, SLOAD(0) + 1), then CALL itself with all gas left, then STOP.
+		let code = vec![
+			0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00,
+			0x60, 0x00, 0x30, 0x5a, 0xf1, 0x00,
+		];
+
+		// The outermost frame and two nested ones run, the third call fails.
+		let config = Config { call_stack_limit: 2, ..Config::cancun() };
+		let (executor, reason) = call(&config, state(vec![(address, code)]), address, 1_000_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(executor.storage(address, U256::zero()), U256::from(3));
+	}
+
+	#[test]
+	fn test_static_mode_violation() {
+		let outer = H160::repeat_byte(0xaa);
+		let inner = H160::repeat_byte(0xbb);
+
+		// SSTORE(0, 1).
+		let inner_code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+		// STATICCALL inner with 10000 gas, then SSTORE(1, ISZERO(success)).
+		let mut outer_code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+		outer_code.extend_from_slice(inner.as_bytes());
+		outer_code.extend_from_slice(&[0x61, 0x27, 0x10, 0xfa, 0x15, 0x60, 0x01, 0x55, 0x00]);
+
+		let config = Config::cancun();
+		let state = state(vec![(outer, outer_code), (inner, inner_code)]);
+		let (executor, reason) = call(&config, state, outer, 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(executor.storage(inner, U256::zero()), U256::zero());
+		assert_eq!(executor.storage(outer, U256::one()), U256::one());
+	}
+
+	#[test]
+	fn test_selfdestruct() {
+		let address = H160::repeat_byte(0xaa);
+		let target = H160::repeat_byte(0xbb);
+		// SELFDESTRUCT(target).
+		let mut code = vec![0x73];
+		code.extend_from_slice(target.as_bytes());
+		code.push(0xff);
+
+		let mut state = state(vec![(address, code)]);
+		state.entry(address).or_default().balance = U256::from(10);
+
+		for (config, deleted) in [(Config::shanghai(), true), (Config::cancun(), false)] {
+			let (executor, reason) = call(&config, state.clone(), address, 100_000);
+			assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Suicided));
+
+			let backend = commit(executor);
+			assert_eq!(backend.exists(address), !deleted);
+			assert_eq!(backend.basic(target).balance, U256::from(10));
+		}
+	}
+
+	#[test]
+	fn test_transient_storage_revert() {
+		let outer = H160::repeat_byte(0xaa);
+		let inner = H160::repeat_byte(0xbb);
+
+		// TSTORE(0, 2), then REVERT.
+		let inner_code = vec![0x60, 0x02, 0x60, 0x00, 0x5d, 0x60, 0x00, 0x60, 0x00, 0xfd];
+		// TSTORE(0, 1), DELEGATECALL inner with 10000 gas, then SSTORE(0, TLOAD(0)).
+		let mut outer_code = vec![0x60, 0x01, 0x60, 0x00, 0x5d, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+		outer_code.extend_from_slice(inner.as_bytes());
+		outer_code.extend_from_slice(&[0x61, 0x27, 0x10, 0xf4, 0x50, 0x60, 0x00, 0x5c, 0x60, 0x00, 0x55, 0x00]);
+
+		let config = Config::cancun();
+		let state = state(vec![(outer, outer_code), (inner, inner_code)]);
+		let (executor, reason) = call(&config, state, outer, 100_000);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(executor.storage(outer, U256::zero()), U256::one());
+		// Transient storage is cleared at the end of the transaction.
+		assert_eq!(executor.transient_storage(outer, U256::zero()), U256::zero());
+	}
+
+	#[test]
+	fn test_code_hash() {
+		let [empty, funded, contract, missing] = [1, 2, 3, 4].map(H160::repeat_byte);
+		let mut state = state(vec![(empty, vec![]), (funded, vec![]), (contract, vec![0x00])]);
+		state.entry(funded).or_default().balance = U256::one();
+
+		let config = Config::cancun();
+		let executor = executor(&config, state);
+		assert_eq!(executor.code_hash(empty), H256::zero());
+		assert_eq!(executor.code_hash(missing), H256::zero());
+		assert_eq!(executor.code_hash(contract), executor.keccak256_h256(&[0x00]));
+		assert_eq!(
+			executor.code_hash(funded),
+			H256::from_str("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470").unwrap(),
+		);
+	}
+
+	#[test]
+	fn test_step_limit() {
+		let address = H160::repeat_byte(0xaa);
+		// PUSH1 1, PUSH1 1, DIV, then STOP.
+		let code = vec![0x60, 0x01, 0x60, 0x01, 0x04, 0x00];
+
+		// DIV weighs two steps, so it does not fit after the two pushes, and
+		// the executor stops rather than running it out of budget.
+		let config = Config::cancun();
+		let mut executor = executor(&config, state(vec![(address, code)]));
+		executor.enter_call(H160::zero(), address, U256::zero(), Vec::new(), 100_000);
+		assert_eq!(executor.execute(3), (2, ExitReason::StepLimitReached));
+		assert_eq!(executor.execute(3), (3, ExitReason::Succeed(ExitSucceed::Stopped)));
+	}
+
+	#[test]
+	fn test_precompile_call() {
+		fn reverse(input: &PrecompileInput) -> PrecompileResult {
+			if input.gas_limit < 100 {
+				return Err(ExitError::OutOfGas.into())
+			}
+			Ok(PrecompileOutput {
+				exit_status: ExitSucceed::Returned,
+				cost: 100,
+				output: input.input.iter().rev().copied().collect(),
+			})
+		}
+
+		let address = H160::repeat_byte(0x01);
+		let mut precompiles = BTreeMap::new();
+		precompiles.insert(address, reverse as PrecompileFn);
+		let config = Config::cancun();
+
+		for (gas_limit, reason, output) in [
+			(1000, ExitReason::Succeed(ExitSucceed::Returned), vec![3, 2, 1]),
+			(50, ExitReason::Error(ExitError::OutOfGas), vec![]),
+		] {
+			let mut executor = StackExecutor::new_with_precompiles(MemoryBackend::default(), &config, &precompiles);
+			executor.enter_call(H160::zero(), address, U256::zero(), vec![1, 2, 3], gas_limit);
+
+			assert_eq!(executor.execute(u64::MAX).1, reason);
+			assert_eq!(executor.return_value(), &output[..]);
+			assert_eq!(executor.used_gas(), gas_limit.min(100));
+		}
+	}
+}
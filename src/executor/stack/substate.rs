@@ -0,0 +1,286 @@
+use alloc::{boxed::Box, collections::{BTreeMap, BTreeSet}, vec::Vec};
+use core::mem;
+use crate::backend::{Apply, Backend, Basic, Log};
+use crate::{ExitError, Transfer, H160, H256, U256};
+
+/// An account changed in a substate.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StackAccount {
+	/// Balance and nonce.
+	pub basic: Basic,
+	/// New code, if it was changed.
+	pub code: Option<Vec<u8>>,
+	/// Whether the storage of the account was reset, so that slots not
+	/// written since read as zero.
+	pub reset: bool,
+}
+
+/// Changes made to the world state by a call frame and the frames it
+/// returned from successfully.
+///
+/// Substates are nested: `enter` opens a substate for a new call frame on
+/// top of the current one, `exit_commit` merges it into its parent and
+/// `exit_revert` discards it. Lookups fall through to the parents, and
+/// return `None` when the change is not known, in which case the value has
+/// to be read from the `Backend`.
+#[derive(Clone, Debug, Default)]
+pub struct StackSubstate {
+	parent: Option<Box<Self>>,
+	logs: Vec<Log>,
+	accounts: BTreeMap<H160, StackAccount>,
+	storages: BTreeMap<(H160, U256), U256>,
+	deletes: BTreeSet<H160>,
+	created: BTreeSet<H160>,
+	touched: BTreeSet<H160>,
+}
+
+impl StackSubstate {
+	/// Create an empty substate.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			parent: None,
+			logs: Vec::new(),
+			accounts: BTreeMap::new(),
+			storages: BTreeMap::new(),
+			deletes: BTreeSet::new(),
+			created: BTreeSet::new(),
+			touched: BTreeSet::new(),
+		}
+	}
+
+	/// Logs emitted so far.
+	#[must_use]
+	pub fn logs(&self) -> &[Log] {
+		&self.logs
+	}
+
+	/// Open a substate for a new call frame.
+	pub fn enter(&mut self) {
+		let parent = mem::take(self);
+		self.parent = Some(Box::new(parent));
+	}
+
+	/// Close the current substate, merging its changes into the parent.
+	///
+	/// # Panics
+	///
+	/// Panics if there is no substate to exit.
+	pub fn exit_commit(&mut self) {
+		let mut exited = *self.parent.take().expect("cannot commit on root substate");
+		mem::swap(&mut exited, self);
+
+		self.logs.append(&mut exited.logs);
+
+		let resets = exited.accounts.iter().filter(|(_, account)| account.reset).map(|(address, _)| *address);
+		for address in resets {
+			self.storages.retain(|(storage_address, _), _| *storage_address != address);
+		}
+
+		for (address, mut account) in mem::take(&mut exited.accounts) {
+			account.reset |= self.accounts.get(&address).is_some_and(|parent| parent.reset);
+			self.accounts.insert(address, account);
+		}
+		self.storages.append(&mut exited.storages);
+		self.deletes.append(&mut exited.deletes);
+		self.created.append(&mut exited.created);
+		self.touched.append(&mut exited.touched);
+	}
+
+	/// Close the current substate, discarding its changes.
+	///
+	/// # Panics
+	///
+	/// Panics if there is no substate to exit.
+	pub fn exit_revert(&mut self) {
+		*self = *self.parent.take().expect("cannot revert on root substate");
+	}
+
+	/// Account as changed in this substate or its parents.
+	#[must_use]
+	pub fn known_account(&self, address: H160) -> Option<&StackAccount> {
+		self.accounts.get(&address).or_else(|| self.parent.as_ref()?.known_account(address))
+	}
+
+	/// Balance and nonce, if changed.
+	#[must_use]
+	pub fn known_basic(&self, address: H160) -> Option<Basic> {
+		self.known_account(address).map(|account| account.basic.clone())
+	}
+
+	/// Code, if changed.
+	#[must_use]
+	pub fn known_code(&self, address: H160) -> Option<Vec<u8>> {
+		self.known_account(address).and_then(|account| account.code.clone())
+	}
+
+	/// Storage value, if written or reset.
+	#[must_use]
+	pub fn known_storage(&self, address: H160, index: U256) -> Option<U256> {
+		if let Some(value) = self.storages.get(&(address, index)) {
+			return Some(*value)
+		}
+
+		if self.accounts.get(&address).is_some_and(|account| account.reset) {
+			return Some(U256::zero())
+		}
+
+		self.parent.as_ref()?.known_storage(address, index)
+	}
+
+	/// Storage value at the beginning of the transaction, if the storage of
+	/// the account was reset since.
+	#[must_use]
+	pub fn known_original_storage(&self, address: H160) -> Option<U256> {
+		if self.accounts.get(&address).is_some_and(|account| account.reset) {
+			return Some(U256::zero())
+		}
+
+		self.parent.as_ref()?.known_original_storage(address)
+	}
+
+	/// Whether the account is marked to be deleted.
+	#[must_use]
+	pub fn deleted(&self, address: H160) -> bool {
+		self.deletes.contains(&address) || self.parent.as_ref().is_some_and(|parent| parent.deleted(address))
+	}
+
+	/// Whether the account was created in this transaction (EIP-6780).
+	#[must_use]
+	pub fn created(&self, address: H160) -> bool {
+		self.created.contains(&address) || self.parent.as_ref().is_some_and(|parent| parent.created(address))
+	}
+
+	/// Whether the account was touched (EIP-161).
+	#[must_use]
+	pub fn touched(&self, address: H160) -> bool {
+		self.touched.contains(&address) || self.parent.as_ref().is_some_and(|parent| parent.touched(address))
+	}
+
+	fn account_mut<B: Backend>(&mut self, address: H160, backend: &B) -> &mut StackAccount {
+		if !self.accounts.contains_key(&address) {
+			let account = self.known_account(address).map_or_else(
+				|| StackAccount {
+					basic: backend.basic(address),
+					code: None,
+					reset: false,
+				},
+				|account| StackAccount { reset: false, ..account.clone() },
+			);
+			self.accounts.insert(address, account);
+		}
+
+		self.accounts.get_mut(&address).expect("account was just inserted")
+	}
+
+	/// Increment the nonce of an account.
+	pub fn inc_nonce<B: Backend>(&mut self, address: H160, backend: &B) {
+		let basic = &mut self.account_mut(address, backend).basic;
+		basic.nonce = basic.nonce.saturating_add(U256::one());
+	}
+
+	/// Set a storage value.
+	pub fn set_storage(&mut self, address: H160, index: U256, value: U256) {
+		self.storages.insert((address, index), value);
+	}
+
+	/// Clear the storage of an account.
+	pub fn reset_storage<B: Backend>(&mut self, address: H160, backend: &B) {
+		self.storages.retain(|(storage_address, _), _| *storage_address != address);
+		self.account_mut(address, backend).reset = true;
+	}
+
+	/// Append a log.
+	pub fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) {
+		self.logs.push(Log { address, topics, data });
+	}
+
+	/// Mark an account to be deleted.
+	pub fn set_deleted(&mut self, address: H160) {
+		self.deletes.insert(address);
+	}
+
+	/// Mark an account as created in this transaction (EIP-6780).
+	pub fn set_created(&mut self, address: H160) {
+		self.created.insert(address);
+	}
+
+	/// Mark an account as touched (EIP-161).
+	pub fn touch(&mut self, address: H160) {
+		self.touched.insert(address);
+	}
+
+	/// Set the code of an account.
+	pub fn set_code<B: Backend>(&mut self, address: H160, code: Vec<u8>, backend: &B) {
+		self.account_mut(address, backend).code = Some(code);
+	}
+
+	/// Burn the whole balance of an account.
+	pub fn reset_balance<B: Backend>(&mut self, address: H160, backend: &B) {
+		self.account_mut(address, backend).basic.balance = U256::zero();
+	}
+
+	/// Take `value` from the balance of an account.
+	pub fn withdraw<B: Backend>(&mut self, address: H160, value: U256, backend: &B) -> Result<(), ExitError> {
+		let basic = &mut self.account_mut(address, backend).basic;
+		if basic.balance < value {
+			return Err(ExitError::OutOfFund)
+		}
+		basic.balance -= value;
+		Ok(())
+	}
+
+	/// Add `value` to the balance of an account.
+	pub fn deposit<B: Backend>(&mut self, address: H160, value: U256, backend: &B) {
+		let basic = &mut self.account_mut(address, backend).basic;
+		basic.balance = basic.balance.saturating_add(value);
+	}
+
+	/// Move `value` from `source` to `target`.
+	pub fn transfer<B: Backend>(&mut self, transfer: &Transfer, backend: &B) -> Result<(), ExitError> {
+		self.withdraw(transfer.source, transfer.value, backend)?;
+		self.deposit(transfer.target, transfer.value, backend);
+		Ok(())
+	}
+
+	/// Split the outermost substate into the account changes and logs to
+	/// apply to `backend`.
+	///
+	/// # Panics
+	///
+	/// Panics if a substate is still open.
+	#[must_use]
+	pub fn deconstruct<B: Backend>(mut self, backend: &B) -> (Vec<Apply<BTreeMap<U256, U256>>>, Vec<Log>) {
+		assert!(self.parent.is_none(), "cannot deconstruct a substate with open frames");
+
+		let mut storages: BTreeMap<H160, BTreeMap<U256, U256>> = BTreeMap::new();
+		for ((address, index), value) in mem::take(&mut self.storages) {
+			storages.entry(address).or_default().insert(index, value);
+		}
+
+		let addresses: BTreeSet<H160> =
+			self.accounts.keys().chain(storages.keys()).chain(self.touched.iter()).copied().collect();
+
+		let mut applies = Vec::new();
+		for address in addresses {
+			if self.deletes.contains(&address) {
+				continue
+			}
+
+			let (basic, code, reset_storage) = match self.accounts.remove(&address) {
+				Some(account) => (account.basic, account.code, account.reset),
+				None => (backend.basic(address), None, false),
+			};
+			applies.push(Apply::Modify {
+				address,
+				basic,
+				code,
+				storage: storages.remove(&address).unwrap_or_default(),
+				reset_storage,
+			});
+		}
+		applies.extend(self.deletes.into_iter().map(|address| Apply::Delete { address }));
+
+		(applies, self.logs)
+	}
+}
@@ -0,0 +1,348 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::cmp::min;
+use crate::backend::{Apply, Backend, Log};
+use crate::gasometer::{self, Gasometer, TransactionCost};
+use crate::precompile::PrecompileSet;
+use crate::{Accessed, Config, CreateScheme, ExitError, ExitReason, H160, U256};
+use super::{keccak256, StackExecutor};
+
+/// A transaction to execute. Legacy and EIP-2930 transactions give their gas
+/// price as both the maximum fee and the maximum priority fee.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Transaction {
+	/// Sender of the transaction.
+	pub caller: H160,
+	/// Nonce of the transaction.
+	pub nonce: U256,
+	/// Gas limit of the transaction.
+	pub gas_limit: u64,
+	/// Maximum fee per gas (EIP-1559).
+	pub max_fee_per_gas: U256,
+	/// Maximum priority fee per gas (EIP-1559).
+	pub max_priority_fee_per_gas: U256,
+	/// Value transferred.
+	pub value: U256,
+	/// Call data, or init code of a create.
+	pub data: Vec<u8>,
+	/// Access list (EIP-2930).
+	pub access_list: Vec<(H160, Vec<U256>)>,
+}
+
+/// Reason a transaction is invalid, in which case it has no effect at all.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransactError {
+	/// The nonce is below the sender nonce.
+	NonceTooLow,
+	/// The nonce is above the sender nonce.
+	NonceTooHigh,
+	/// The sender nonce cannot be incremented any more (EIP-2681).
+	NonceOverflow,
+	/// The sender has code (EIP-3607).
+	SenderNotEoa,
+	/// The maximum fee per gas is below the block base fee.
+	FeeCapTooLow,
+	/// The maximum priority fee per gas is above the maximum fee per gas.
+	PriorityFeeTooHigh,
+	/// The sender cannot pay for the gas limit and the value.
+	InsufficientFunds,
+	/// The gas limit is below the intrinsic gas, or above the cap.
+	IntrinsicGas(ExitError),
+}
+
+/// Outcome of an executed transaction.
+#[derive(Clone, Debug)]
+pub struct TransactOutcome {
+	/// Exit reason of the outermost frame.
+	pub exit_reason: ExitReason,
+	/// Data returned by the outermost frame.
+	pub return_value: Vec<u8>,
+	/// Gas used, after refunds.
+	pub used_gas: u64,
+	/// Logs of the frames that were not reverted.
+	pub logs: Vec<Log>,
+	/// Address of the created contract, for a successful create.
+	pub created_address: Option<H160>,
+	/// Changes to apply to the backend, fees and nonce included.
+	pub applies: Vec<Apply<BTreeMap<U256, U256>>>,
+}
+
+/// Execute a message call transaction to `address`.
+pub fn transact_call<B: Backend, P: PrecompileSet>(
+	backend: &B,
+	config: &Config,
+	precompiles: &P,
+	transaction: Transaction,
+	address: H160,
+) -> Result<TransactOutcome, TransactError> {
+	let cost = gasometer::call_transaction_cost(&transaction.data, &transaction.access_list, 0);
+	transact(backend, config, precompiles, transaction, cost, Some(address))
+}
+
+/// Execute a contract creation transaction.
+pub fn transact_create<B: Backend, P: PrecompileSet>(
+	backend: &B,
+	config: &Config,
+	precompiles: &P,
+	transaction: Transaction,
+) -> Result<TransactOutcome, TransactError> {
+	let cost = gasometer::create_transaction_cost(&transaction.data, &transaction.access_list);
+	transact(backend, config, precompiles, transaction, cost, None)
+}
+
+/// Base fee burnt per unit of gas, zero before EIP-1559.
+fn base_fee<B: Backend>(backend: &B, config: &Config) -> U256 {
+	if config.has_base_fee {
+		backend.block_base_fee_per_gas()
+	} else {
+		U256::zero()
+	}
+}
+
+/// Check a transaction against the sender account and the block, returning
+/// the effective gas price.
+fn validate<B: Backend>(
+	backend: &B,
+	config: &Config,
+	transaction: &Transaction,
+	cost: TransactionCost,
+) -> Result<U256, TransactError> {
+	if transaction.max_priority_fee_per_gas > transaction.max_fee_per_gas {
+		return Err(TransactError::PriorityFeeTooHigh)
+	}
+	let base_fee = base_fee(backend, config);
+	if transaction.max_fee_per_gas < base_fee {
+		return Err(TransactError::FeeCapTooLow)
+	}
+
+	let basic = backend.basic(transaction.caller);
+	if transaction.nonce >= U256::from(u64::MAX) {
+		return Err(TransactError::NonceOverflow)
+	}
+	if transaction.nonce < basic.nonce {
+		return Err(TransactError::NonceTooLow)
+	}
+	if transaction.nonce > basic.nonce {
+		return Err(TransactError::NonceTooHigh)
+	}
+
+	// Accounts delegated with EIP-7702 may still send transactions.
+	let code = backend.code(transaction.caller);
+	let is_delegated = config.has_authorization_list && code.starts_with(&[0xef, 0x01, 0x00]);
+	if !code.is_empty() && !is_delegated {
+		return Err(TransactError::SenderNotEoa)
+	}
+
+	Gasometer::new(transaction.gas_limit, config)
+		.record_transaction(cost)
+		.map_err(TransactError::IntrinsicGas)?;
+
+	let max_cost = U256::from(transaction.gas_limit)
+		.checked_mul(transaction.max_fee_per_gas)
+		.and_then(|fee| fee.checked_add(transaction.value));
+	if max_cost.is_none_or(|max_cost| max_cost > basic.balance) {
+		return Err(TransactError::InsufficientFunds)
+	}
+
+	if config.has_base_fee {
+		Ok(min(transaction.max_fee_per_gas, base_fee.saturating_add(transaction.max_priority_fee_per_gas)))
+	} else {
+		Ok(transaction.max_fee_per_gas)
+	}
+}
+
+fn transact<B: Backend, P: PrecompileSet>(
+	backend: &B,
+	config: &Config,
+	precompiles: &P,
+	transaction: Transaction,
+	cost: TransactionCost,
+	address: Option<H160>,
+) -> Result<TransactOutcome, TransactError> {
+	let gas_price = validate(backend, config, &transaction, cost)?;
+	let Transaction { caller, nonce, gas_limit, value, data, access_list, .. } = transaction;
+	let coinbase = backend.block_coinbase();
+
+	let mut executor = StackExecutor::new_with_precompiles(backend, config, precompiles);
+	executor.set_transaction_env(caller, gas_price);
+
+	let target = address.unwrap_or_else(|| CreateScheme::Legacy { caller }.address(nonce, keccak256));
+	executor.accessed = Accessed::for_transaction(config, caller, target, coinbase, precompiles.addresses(), &access_list);
+
+	// Cannot fail, the balance was checked against the maximum fee.
+	let _ = executor.substate.withdraw(caller, U256::from(gas_limit) * gas_price, backend);
+
+	executor.transaction_cost = Some(cost);
+	match address {
+		Some(address) => {
+			executor.substate.inc_nonce(caller, backend);
+			executor.enter_call(caller, address, value, data, gas_limit);
+		},
+		None => executor.enter_create(caller, CreateScheme::Legacy { caller }, value, data, gas_limit),
+	}
+
+	let (_, exit_reason) = executor.execute(u64::MAX);
+	let used_gas = executor.used_gas();
+
+	executor.substate.deposit(caller, U256::from(gas_limit - used_gas) * gas_price, backend);
+	let priority_fee = gas_price - base_fee(backend, config);
+	executor.substate.deposit(coinbase, U256::from(used_gas) * priority_fee, backend);
+
+	let return_value = executor.return_value().to_vec();
+	let created_address = executor.created_address();
+	let (_, substate) = executor.into_parts();
+	let (applies, logs) = substate.deconstruct(backend);
+
+	Ok(TransactOutcome { exit_reason, return_value, used_gas, logs, created_address, applies })
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::{collections::BTreeMap, vec, vec::Vec};
+	use super::{transact_call, Transaction, TransactError};
+	use crate::backend::{ApplyBackend, Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::executor::StackExecutor;
+	use crate::precompile::{self, PrecompileFn, PrecompileInput, PrecompileOutput, PrecompileResult};
+	use crate::{gasometer, Config, ExitError, ExitReason, ExitSucceed, Handler, H160, U256};
+
+	#[test]
+	fn test_transact_call() {
+		let caller = H160::repeat_byte(0xaa);
+		let target = H160::repeat_byte(0xbb);
+		let coinbase = H160::repeat_byte(0xcc);
+
+		let mut state = BTreeMap::new();
+		state.insert(caller, MemoryAccount { balance: U256::from(1_000_000_000), ..MemoryAccount::default() });
+		let vicinity = MemoryVicinity {
+			block_coinbase: coinbase,
+			block_base_fee_per_gas: U256::from(7),
+			..MemoryVicinity::default()
+		};
+		let mut backend = MemoryBackend::new(vicinity, state);
+
+		let config = Config::cancun();
+		let transaction = Transaction {
+			caller,
+			gas_limit: 30_000,
+			max_fee_per_gas: U256::from(10),
+			max_priority_fee_per_gas: U256::from(2),
+			value: U256::from(1000),
+			data: Vec::new(),
+			..Transaction::default()
+		};
+
+		let outcome = transact_call(&backend, &config, &(), transaction.clone(), target).unwrap();
+		assert_eq!(outcome.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert_eq!(outcome.used_gas, 21_000);
+		backend.apply(outcome.applies, outcome.logs, true);
+
+		// Gas is paid at the base fee of 7 plus the priority fee of 2.
+		assert_eq!(backend.basic(caller).balance, U256::from(1_000_000_000 - 21_000 * 9 - 1000));
+		assert_eq!(backend.basic(caller).nonce, U256::one());
+		assert_eq!(backend.basic(target).balance, U256::from(1000));
+		assert_eq!(backend.basic(coinbase).balance, U256::from(21_000 * 2));
+
+		assert_eq!(transact_call(&backend, &config, &(), transaction, target).unwrap_err(), TransactError::NonceTooLow);
+	}
+
+	#[test]
+	fn test_transact_precompile() {
+		let caller = H160::repeat_byte(0xaa);
+		let mut identity = H160::zero();
+		identity.0[19] = 4;
+
+		let mut state = BTreeMap::new();
+		state.insert(caller, MemoryAccount { balance: U256::from(1_000_000), ..MemoryAccount::default() });
+		let backend = MemoryBackend::new(MemoryVicinity::default(), state);
+
+		let config = Config::cancun();
+		let transaction = Transaction { caller, gas_limit: 30_000, data: vec![1; 33], ..Transaction::default() };
+		let outcome = transact_call(&backend, &config, &precompile::frontier(), transaction, identity).unwrap();
+		assert_eq!(outcome.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(outcome.return_value, vec![1; 33]);
+		// 16 gas per non-zero byte of calldata, and 15 plus 3 per word for the identity precompile.
+		assert_eq!(outcome.used_gas, 21_000 + 33 * 16 + 15 + 2 * 3);
+	}
+
+	#[test]
+	fn test_precompile_gas_limit() {
+		fn gas_limit(input: &PrecompileInput) -> PrecompileResult {
+			if input.gas_limit < 100 {
+				return Err(ExitError::OutOfGas.into())
+			}
+			let output = input.gas_limit.to_be_bytes().to_vec();
+			Ok(PrecompileOutput { exit_status: ExitSucceed::Returned, cost: 100, output })
+		}
+
+		let caller = H160::repeat_byte(0xaa);
+		let address = H160::repeat_byte(0xbb);
+		let mut identity = H160::zero();
+		identity.0[19] = 4;
+
+		let mut state = BTreeMap::new();
+		state.insert(caller, MemoryAccount { balance: U256::from(1_000_000), ..MemoryAccount::default() });
+		let backend = MemoryBackend::new(MemoryVicinity::default(), state);
+		let mut precompiles = precompile::frontier();
+		precompiles.insert(address, gas_limit as PrecompileFn);
+
+		// The precompile is given the gas left after the intrinsic gas.
+		let config = Config::cancun();
+		let transaction = Transaction { caller, gas_limit: 30_000, ..Transaction::default() };
+		let outcome = transact_call(&backend, &config, &precompiles, transaction, address).unwrap();
+		assert_eq!(outcome.return_value, 9000_u64.to_be_bytes());
+
+		// The 21 gas of the identity precompile only fit if the intrinsic gas
+		// is ignored.
+		let gas_limit = 21_000 + 33 * 16 + 20;
+		let transaction = Transaction { caller, gas_limit, data: vec![1; 33], ..Transaction::default() };
+		let outcome = transact_call(&backend, &config, &precompiles, transaction, identity).unwrap();
+		assert_eq!(outcome.exit_reason, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(outcome.used_gas, gas_limit);
+	}
+
+	#[test]
+	fn test_transact_revert_floor() {
+		let caller = H160::repeat_byte(0xaa);
+		let target = H160::repeat_byte(0xbb);
+
+		let mut state = BTreeMap::new();
+		state.insert(caller, MemoryAccount { balance: U256::from(1_000_000), ..MemoryAccount::default() });
+		// REVERT(0, 0).
+		state.insert(target, MemoryAccount { code: vec![0x60, 0x00, 0x60, 0x00, 0xfd], ..MemoryAccount::default() });
+		let backend = MemoryBackend::new(MemoryVicinity::default(), state);
+
+		let config = Config::prague();
+		let transaction = Transaction { caller, gas_limit: 100_000, data: vec![1; 1000], ..Transaction::default() };
+		let outcome = transact_call(&backend, &config, &(), transaction, target).unwrap();
+		assert!(matches!(outcome.exit_reason, ExitReason::Revert(_)));
+		// The calldata floor of 10 gas per token, 4 tokens per non-zero byte,
+		// is above the 16 gas per non-zero byte and the execution cost.
+		assert_eq!(outcome.used_gas, 21_000 + 1000 * 4 * 10);
+	}
+
+	#[test]
+	fn test_intrinsic_gas_above_limit() {
+		let caller = H160::repeat_byte(0xaa);
+		let target = H160::repeat_byte(0xbb);
+
+		let mut state = BTreeMap::new();
+		state.insert(caller, MemoryAccount { balance: U256::from(1_000_000), ..MemoryAccount::default() });
+		// SSTORE(0, 1).
+		state.insert(target, MemoryAccount { code: vec![0x60, 0x01, 0x60, 0x00, 0x55], ..MemoryAccount::default() });
+		let backend = MemoryBackend::new(MemoryVicinity::default(), state);
+
+		let config = Config::cancun();
+		let transaction = Transaction { caller, gas_limit: 20_000, ..Transaction::default() };
+		assert_eq!(
+			transact_call(&backend, &config, &(), transaction, target).unwrap_err(),
+			TransactError::IntrinsicGas(ExitError::OutOfGas),
+		);
+
+		// The outermost frame is not entered at all.
+		let mut executor = StackExecutor::new(backend, &config);
+		executor.transaction_cost = Some(gasometer::call_transaction_cost(&[], &[], 0));
+		executor.enter_call(caller, target, U256::zero(), Vec::new(), 20_000);
+		assert_eq!(executor.execute(u64::MAX).1, ExitReason::Error(ExitError::OutOfGas));
+		assert_eq!(executor.used_gas(), 20_000);
+		assert_eq!(executor.storage(target, U256::zero()), U256::zero());
+	}
+}
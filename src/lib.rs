@@ -13,6 +13,14 @@
 
 extern crate alloc;
 
+mod estimate;
+pub mod backend;
+pub mod executor;
+pub mod precompile;
+pub mod receipt;
+
 pub use evm_core::*;
 pub use evm_runtime::*;
+pub use evm_gasometer as gasometer;
+pub use crate::estimate::estimate_gas;
 
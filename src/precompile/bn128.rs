@@ -0,0 +1,179 @@
+use alloc::{vec, vec::Vec};
+use core::cmp::min;
+use bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+use crate::ExitError;
+use super::{charge, returned, PrecompileFailure, PrecompileInput, PrecompileResult};
+
+/// Length of an encoded G1 point.
+const G1_LEN: usize = 64;
+/// Length of an encoded pair of a G1 and a G2 point.
+const PAIR_LEN: usize = 192;
+
+/// The `alt_bn128` addition precompile at 0x06 (EIP-196).
+///
+/// The input is two G1 points, right-padded with zeros. Returns their sum.
+pub fn bn128_add(input: &PrecompileInput) -> PrecompileResult {
+	let cost = input.config.gas_bn128_add;
+	charge(input, cost)?;
+
+	let data = padded(input.input, 2 * G1_LEN);
+	let sum = read_g1(&data[..G1_LEN])? + read_g1(&data[G1_LEN..])?;
+	returned(input, cost, write_g1(sum))
+}
+
+/// The `alt_bn128` scalar multiplication precompile at 0x07 (EIP-196).
+///
+/// The input is a G1 point and a 32-byte scalar, right-padded with zeros.
+/// Returns the point multiplied by the scalar.
+pub fn bn128_mul(input: &PrecompileInput) -> PrecompileResult {
+	let cost = input.config.gas_bn128_mul;
+	charge(input, cost)?;
+
+	let data = padded(input.input, G1_LEN + 32);
+	let point = read_g1(&data[..G1_LEN])?;
+	let scalar = Fr::from_slice(&data[G1_LEN..]).map_err(|_| invalid_input())?;
+	returned(input, cost, write_g1(point * scalar))
+}
+
+/// The `alt_bn128` pairing check precompile at 0x08 (EIP-197).
+///
+/// The input is a sequence of pairs of a G1 and a G2 point, each G2
+/// coordinate encoded with its imaginary part first. Returns one as a 32-byte
+/// word if the product of the pairings is one, zero otherwise. Any input
+/// length but a multiple of 192 bytes is invalid.
+pub fn bn128_pairing(input: &PrecompileInput) -> PrecompileResult {
+	if !input.input.len().is_multiple_of(PAIR_LEN) {
+		return Err(invalid_input())
+	}
+	let points = (input.input.len() / PAIR_LEN) as u64;
+	let cost = input.config.gas_bn128_pairing_per_point
+		.saturating_mul(points)
+		.saturating_add(input.config.gas_bn128_pairing_base);
+	charge(input, cost)?;
+
+	let pairs = input.input
+		.chunks(PAIR_LEN)
+		.map(|pair| Ok((read_g1(&pair[..G1_LEN])?, read_g2(&pair[G1_LEN..])?)))
+		.collect::<Result<Vec<_>, PrecompileFailure>>()?;
+
+	let mut output = vec![0; 32];
+	output[31] = u8::from(bn::pairing_batch(&pairs) == Gt::one());
+	returned(input, cost, output)
+}
+
+fn invalid_input() -> PrecompileFailure {
+	ExitError::InvalidPrecompileInput.into()
+}
+
+/// The first `len` bytes of `input`, right-padded with zeros.
+fn padded(input: &[u8], len: usize) -> Vec<u8> {
+	let mut data = vec![0; len];
+	let copied = min(input.len(), len);
+	data[..copied].copy_from_slice(&input[..copied]);
+	data
+}
+
+/// A base field element, invalid unless below the field modulus.
+fn read_fq(bytes: &[u8]) -> Result<Fq, PrecompileFailure> {
+	Fq::from_slice(bytes).map_err(|_| invalid_input())
+}
+
+/// A G1 point from its coordinates, the point at infinity being (0, 0).
+fn read_g1(bytes: &[u8]) -> Result<G1, PrecompileFailure> {
+	let x = read_fq(&bytes[..32])?;
+	let y = read_fq(&bytes[32..64])?;
+
+	if x.is_zero() && y.is_zero() {
+		return Ok(G1::zero())
+	}
+	AffineG1::new(x, y).map(Into::into).map_err(|_| invalid_input())
+}
+
+/// A G2 point from its coordinates, invalid unless it is in the subgroup.
+fn read_g2(bytes: &[u8]) -> Result<G2, PrecompileFailure> {
+	let x = Fq2::new(read_fq(&bytes[32..64])?, read_fq(&bytes[..32])?);
+	let y = Fq2::new(read_fq(&bytes[96..128])?, read_fq(&bytes[64..96])?);
+
+	if x.is_zero() && y.is_zero() {
+		return Ok(G2::zero())
+	}
+	AffineG2::new(x, y).map(Into::into).map_err(|_| invalid_input())
+}
+
+/// Coordinates of a G1 point, (0, 0) for the point at infinity.
+fn write_g1(point: G1) -> Vec<u8> {
+	let mut output = vec![0; G1_LEN];
+	if let Some(point) = AffineG1::from_jacobian(point) {
+		let _ = point.x().to_big_endian(&mut output[..32]);
+		let _ = point.y().to_big_endian(&mut output[32..]);
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{bn128_add, bn128_mul, bn128_pairing};
+	use crate::precompile::{run, run_with_config, PrecompileFailure};
+	use crate::{Config, ExitError};
+
+	/// The G1 generator, (1, 2).
+	const G1: &str = concat!(
+		"0000000000000000000000000000000000000000000000000000000000000001",
+		"0000000000000000000000000000000000000000000000000000000000000002",
+	);
+	/// The negated G1 generator, (1, p - 2).
+	const G1_NEG: &str = concat!(
+		"0000000000000000000000000000000000000000000000000000000000000001",
+		"30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd45",
+	);
+	/// The G2 generator.
+	const G2: &str = concat!(
+		"198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+		"1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+		"090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+		"12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+	);
+	/// Twice the G1 generator.
+	const G1_DOUBLE: &str = concat!(
+		"030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3",
+		"15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4",
+	);
+
+	#[test]
+	fn test_bn128_add_mul() {
+		let input = hex::decode([G1, G1].concat()).unwrap();
+		let output = run_with_config(bn128_add, &input, 500, &Config::byzantium()).unwrap();
+		assert_eq!((output.cost, hex::encode(output.output)), (500, G1_DOUBLE.into()));
+		assert_eq!(run(bn128_add, &input, 150).unwrap().cost, 150);
+
+		// An empty input adds the point at infinity to itself.
+		assert_eq!(run(bn128_add, &[], 150).unwrap().output, vec![0; 64]);
+
+		let mut input = hex::decode(G1).unwrap();
+		input.extend_from_slice(&[0; 31]);
+		input.push(2);
+		let output = run(bn128_mul, &input, 6000).unwrap();
+		assert_eq!((output.cost, hex::encode(output.output)), (6000, G1_DOUBLE.into()));
+
+		// (1, 3) is not on the curve.
+		input[63] = 3;
+		assert_eq!(run(bn128_mul, &input, 6000).unwrap_err(), PrecompileFailure::from(ExitError::InvalidPrecompileInput));
+	}
+
+	#[test]
+	fn test_bn128_pairing() {
+		let output = run(bn128_pairing, &[], 45000).unwrap();
+		assert_eq!(output.output[31], 1);
+
+		let input = hex::decode([G1, G2, G1_NEG, G2].concat()).unwrap();
+		let output = run_with_config(bn128_pairing, &input, 260_000, &Config::byzantium()).unwrap();
+		assert_eq!((output.cost, output.output[31]), (260_000, 1));
+
+		let input = hex::decode([G1, G2, G1, G2].concat()).unwrap();
+		let output = run(bn128_pairing, &input, 113_000).unwrap();
+		assert_eq!((output.cost, output.output[31]), (113_000, 0));
+
+		assert!(run(bn128_pairing, &input[1..], 113_000).is_err());
+		assert!(run(bn128_pairing, &input, 112_999).is_err());
+	}
+}
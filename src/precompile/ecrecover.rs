@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+use super::{returned, PrecompileInput, PrecompileResult};
+
+/// The ecrecover precompile at 0x01.
+///
+/// The input is the message hash, `v`, `r` and `s` as 32-byte words,
+/// right-padded with zeros. Returns the signer address left-padded to 32
+/// bytes, or nothing if `v` is not 27 or 28 or if `r` or `s` is not in the
+/// range 1 to n-1. Unlike transaction signatures (EIP-2), `s` may be in the
+/// upper half of the range.
+pub fn ecrecover(input: &PrecompileInput) -> PrecompileResult {
+	let mut data = [0; 128];
+	let len = min(input.input.len(), data.len());
+	data[..len].copy_from_slice(&input.input[..len]);
+
+	let output = recover(&data).map_or_else(Vec::new, |address| address.to_vec());
+	returned(input, 3000, output)
+}
+
+fn recover(data: &[u8; 128]) -> Option<[u8; 32]> {
+	let (hash, v, signature) = (&data[..32], &data[32..64], &data[64..]);
+	if v[..31].iter().any(|&byte| byte != 0) || !(v[31] == 27 || v[31] == 28) {
+		return None
+	}
+
+	let mut recovery_id = RecoveryId::new(v[31] == 28, false);
+	let mut signature = Signature::from_slice(signature).ok()?;
+	// k256 only verifies signatures with a low `s`, and negating `s` flips
+	// the parity of the recovered point.
+	if let Some(normalized) = signature.normalize_s() {
+		signature = normalized;
+		recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), false);
+	}
+
+	let key = VerifyingKey::recover_from_prehash(hash, &signature, recovery_id).ok()?;
+	let hash = Keccak256::digest(&key.to_encoded_point(false).as_bytes()[1..]);
+
+	let mut output = [0; 32];
+	output[12..].copy_from_slice(&hash[12..]);
+	Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::ecrecover;
+	use crate::precompile::run;
+
+	#[test]
+	fn test_ecrecover() {
+		let mut input = hex::decode(concat!(
+			"38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e",
+			"000000000000000000000000000000000000000000000000000000000000001b",
+			"38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e",
+			"789d1dd423d25f0772d2748d60f7e4b81bb14d086eba8e8e8efb6dcff8a4ae02",
+		))
+		.unwrap();
+		let output = run(ecrecover, &input, 3000).unwrap();
+		assert_eq!(output.cost, 3000);
+		assert_eq!(hex::encode(output.output), "000000000000000000000000ceaccac640adf55b2028469bd36ba501f28b699d");
+
+		// The same signature with `s` in the upper half and `v` flipped.
+		let high_s = hex::decode("8762e22bdc2da0f88d2d8b729f081b469efd8fde408e11ad30d6f0bcd791933f").unwrap();
+		input[63] = 28;
+		input[96..].copy_from_slice(&high_s);
+		let output = run(ecrecover, &input, 3000).unwrap();
+		assert_eq!(hex::encode(output.output), "000000000000000000000000ceaccac640adf55b2028469bd36ba501f28b699d");
+
+		// Any `v` other than 27 or 28 gives no output, but still costs gas.
+		input[63] = 29;
+		let output = run(ecrecover, &input, 3000).unwrap();
+		assert_eq!((output.cost, output.output.len()), (3000, 0));
+		assert!(run(ecrecover, &input, 2999).is_err());
+	}
+}
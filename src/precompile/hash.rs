@@ -0,0 +1,37 @@
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use super::{linear_cost, returned, PrecompileInput, PrecompileResult};
+
+/// The SHA-256 precompile at 0x02.
+pub fn sha256(input: &PrecompileInput) -> PrecompileResult {
+	let cost = linear_cost(input.input.len(), 60, 12);
+	returned(input, cost, Sha256::digest(input.input).to_vec())
+}
+
+/// The RIPEMD-160 precompile at 0x03, returning the hash left-padded to 32
+/// bytes.
+pub fn ripemd160(input: &PrecompileInput) -> PrecompileResult {
+	let cost = linear_cost(input.input.len(), 600, 120);
+	let mut output = [0; 32];
+	output[12..].copy_from_slice(&Ripemd160::digest(input.input));
+	returned(input, cost, output.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ripemd160, sha256};
+	use crate::precompile::run;
+
+	#[test]
+	fn test_hashes() {
+		let output = run(sha256, &[], 60).unwrap();
+		assert_eq!(output.cost, 60);
+		assert_eq!(hex::encode(output.output), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+		assert_eq!(run(sha256, b"abc", 72).unwrap().cost, 72);
+
+		let output = run(ripemd160, b"abc", 720).unwrap();
+		assert_eq!(output.cost, 720);
+		assert_eq!(hex::encode(output.output), "0000000000000000000000008eb208f7e05d987a9b044a8e98c6b087f15a0bfc");
+		assert!(run(ripemd160, b"abc", 719).is_err());
+	}
+}
@@ -0,0 +1,7 @@
+use super::{linear_cost, returned, PrecompileInput, PrecompileResult};
+
+/// The identity precompile at 0x04, returning its input.
+pub fn identity(input: &PrecompileInput) -> PrecompileResult {
+	let cost = linear_cost(input.input.len(), 15, 3);
+	returned(input, cost, input.input.to_vec())
+}
@@ -0,0 +1,206 @@
+//! Precompiled contracts, run natively in place of EVM code.
+
+mod bn128;
+mod ecrecover;
+mod hash;
+mod identity;
+mod modexp;
+
+pub use self::bn128::{bn128_add, bn128_mul, bn128_pairing};
+pub use self::ecrecover::ecrecover;
+pub use self::hash::{ripemd160, sha256};
+pub use self::identity::identity;
+pub use self::modexp::modexp;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use crate::{Config, Context, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed, H160};
+
+/// Input of a precompile call.
+#[derive(Clone, Copy, Debug)]
+pub struct PrecompileInput<'a> {
+	/// Call data.
+	pub input: &'a [u8],
+	/// Gas available to the precompile.
+	pub gas_limit: u64,
+	/// Context of the call, with its static mode in `context.is_static`.
+	pub context: &'a Context,
+	/// Configuration of the calling runtime.
+	pub config: &'a Config,
+}
+
+/// Output of a successful precompile call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PrecompileOutput {
+	/// How the precompile succeeded.
+	pub exit_status: ExitSucceed,
+	/// Gas used.
+	pub cost: u64,
+	/// Returned data.
+	pub output: Vec<u8>,
+}
+
+/// Failure of a precompile call.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PrecompileFailure {
+	/// The call failed, consuming all its gas.
+	Error {
+		/// Reason of the failure.
+		exit_status: ExitError,
+	},
+	/// The call reverted, returning data and the unused gas.
+	Revert {
+		/// Reason of the revert.
+		exit_status: ExitRevert,
+		/// Gas used.
+		cost: u64,
+		/// Returned data.
+		output: Vec<u8>,
+	},
+	/// The call failed in a way that aborts the whole execution.
+	Fatal {
+		/// Reason of the failure.
+		exit_status: ExitFatal,
+	},
+}
+
+impl From<ExitError> for PrecompileFailure {
+	fn from(exit_status: ExitError) -> Self {
+		Self::Error { exit_status }
+	}
+}
+
+impl PrecompileFailure {
+	/// Exit reason of the call.
+	#[must_use]
+	pub fn exit_reason(&self) -> ExitReason {
+		match self {
+			Self::Error { exit_status } => (*exit_status).into(),
+			Self::Revert { exit_status, .. } => (*exit_status).into(),
+			Self::Fatal { exit_status } => (*exit_status).into(),
+		}
+	}
+}
+
+/// Result of a precompile call.
+pub type PrecompileResult = Result<PrecompileOutput, PrecompileFailure>;
+
+/// A precompiled contract.
+pub type PrecompileFn = fn(&PrecompileInput) -> PrecompileResult;
+
+/// Set of precompiled contracts, consulted before entering a call frame.
+pub trait PrecompileSet {
+	/// Run the precompile at `address`, or return `None` if there is none,
+	/// in which case the code at `address` runs instead.
+	fn execute(&self, address: H160, input: &PrecompileInput) -> Option<PrecompileResult>;
+	/// Addresses of the precompiles, warm from the start of every
+	/// transaction (EIP-2929).
+	fn addresses(&self) -> Vec<H160>;
+}
+
+/// No precompiles.
+impl PrecompileSet for () {
+	fn execute(&self, _address: H160, _input: &PrecompileInput) -> Option<PrecompileResult> {
+		None
+	}
+
+	fn addresses(&self) -> Vec<H160> {
+		Vec::new()
+	}
+}
+
+impl PrecompileSet for BTreeMap<H160, PrecompileFn> {
+	fn execute(&self, address: H160, input: &PrecompileInput) -> Option<PrecompileResult> {
+		self.get(&address).map(|precompile| precompile(input))
+	}
+
+	fn addresses(&self) -> Vec<H160> {
+		self.keys().copied().collect()
+	}
+}
+
+/// Precompiles of Frontier: ecrecover, SHA-256, RIPEMD-160 and identity at
+/// 0x01 to 0x04.
+#[must_use]
+pub fn frontier() -> BTreeMap<H160, PrecompileFn> {
+	let mut precompiles = BTreeMap::new();
+	precompiles.insert(address(1), ecrecover as PrecompileFn);
+	precompiles.insert(address(2), sha256);
+	precompiles.insert(address(3), ripemd160);
+	precompiles.insert(address(4), identity);
+	precompiles
+}
+
+/// Precompiles of Byzantium: those of Frontier, MODEXP at 0x05, and the
+/// `alt_bn128` addition, scalar multiplication and pairing check at 0x06 to
+/// 0x08.
+#[must_use]
+pub fn byzantium() -> BTreeMap<H160, PrecompileFn> {
+	let mut precompiles = frontier();
+	precompiles.insert(address(5), modexp as PrecompileFn);
+	precompiles.insert(address(6), bn128_add);
+	precompiles.insert(address(7), bn128_mul);
+	precompiles.insert(address(8), bn128_pairing);
+	precompiles
+}
+
+/// Address of the precompile with the given index.
+const fn address(index: u8) -> H160 {
+	let mut address = H160::zero();
+	address.0[19] = index;
+	address
+}
+
+/// Cost of `base` plus `word` per 32-byte word of an input of `len` bytes.
+const fn linear_cost(len: usize, base: u64, word: u64) -> u64 {
+	let words = (len as u64).div_ceil(32);
+	base.saturating_add(word.saturating_mul(words))
+}
+
+/// Fail with `OutOfGas` unless `cost` fits in the gas limit.
+fn charge(input: &PrecompileInput, cost: u64) -> Result<(), PrecompileFailure> {
+	if cost > input.gas_limit {
+		return Err(ExitError::OutOfGas.into())
+	}
+	Ok(())
+}
+
+/// Succeed with `output` if `cost` fits in the gas limit, fail with
+/// `OutOfGas` otherwise.
+fn returned(input: &PrecompileInput, cost: u64, output: Vec<u8>) -> PrecompileResult {
+	charge(input, cost)?;
+	Ok(PrecompileOutput { exit_status: ExitSucceed::Returned, cost, output })
+}
+
+#[cfg(test)]
+fn run(precompile: PrecompileFn, input: &[u8], gas_limit: u64) -> PrecompileResult {
+	run_with_config(precompile, input, gas_limit, &Config::cancun())
+}
+
+#[cfg(test)]
+fn run_with_config(precompile: PrecompileFn, input: &[u8], gas_limit: u64, config: &Config) -> PrecompileResult {
+	let context = Context {
+		address: H160::zero(),
+		caller: H160::zero(),
+		apparent_value: crate::U256::zero(),
+		depth: 0,
+		is_static: false,
+	};
+	precompile(&PrecompileInput { input, gas_limit, context: &context, config })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{frontier, identity, run, PrecompileSet};
+	use crate::ExitError;
+
+	#[test]
+	fn test_identity() {
+		let output = run(identity, &[1; 33], 21).unwrap();
+		assert_eq!((output.cost, output.output), (21, vec![1; 33]));
+		assert_eq!(run(identity, &[1; 33], 20).unwrap_err(), ExitError::OutOfGas.into());
+
+		let addresses = frontier().addresses();
+		assert_eq!(addresses.len(), 4);
+		assert_eq!(addresses[3].0[19], 4);
+	}
+}
@@ -0,0 +1,145 @@
+use alloc::{vec, vec::Vec};
+use core::cmp::{max, min};
+use core::convert::TryFrom;
+use crate::{Config, ExitError, U256};
+use super::{charge, returned, PrecompileInput, PrecompileResult};
+
+/// The MODEXP precompile at 0x05 (EIP-198), computing `base ^ exp % mod`.
+///
+/// The input is the lengths of the base, exponent and modulus as 32-byte
+/// words, then their big-endian values, right-padded with zeros. Returns the
+/// result left-padded to the length of the modulus. Pricing follows EIP-198,
+/// EIP-2565 or EIP-7883, and the lengths are bounded by EIP-7823, as set by
+/// the `Config`.
+pub fn modexp(input: &PrecompileInput) -> PrecompileResult {
+	let base_len = length(&read(input.input, 0, 32));
+	let exp_len = length(&read(input.input, 32, 32));
+	let mod_len = length(&read(input.input, 64, 32));
+
+	if let Some(max_size) = input.config.modexp_max_input_size {
+		if [base_len, exp_len, mod_len].iter().any(|&len| len > max_size as u64) {
+			return Err(ExitError::InvalidPrecompileInput.into())
+		}
+	}
+
+	let exp_offset = 96_u64.saturating_add(base_len);
+	let exp_head = U256::from_big_endian(&read(input.input, exp_offset, to_usize(min(exp_len, 32))));
+	let cost = cost(input.config, base_len, exp_len, mod_len, exp_head);
+	charge(input, cost)?;
+
+	// The lengths are bounded by the gas paid from here on.
+	let (base_len, exp_len, mod_len) = (to_usize(base_len), to_usize(exp_len), to_usize(mod_len));
+	if mod_len == 0 {
+		return returned(input, cost, Vec::new())
+	}
+	let base = read(input.input, 96, base_len);
+	let exp = read(input.input, exp_offset, exp_len);
+	let modulus = read(input.input, exp_offset.saturating_add(exp_len as u64), mod_len);
+
+	let result = aurora_engine_modexp::modexp(&base, &exp, &modulus);
+	let mut output = vec![0; mod_len];
+	let len = min(result.len(), mod_len);
+	output[mod_len - len..].copy_from_slice(&result[result.len() - len..]);
+	returned(input, cost, output)
+}
+
+/// Gas cost of a call.
+fn cost(config: &Config, base_len: u64, exp_len: u64, mod_len: u64, exp_head: U256) -> u64 {
+	let max_len = u128::from(max(base_len, mod_len));
+
+	let cost = if config.modexp_eip7883_pricing {
+		let complexity = if max_len <= 32 { 16 } else { 2 * max_len.div_ceil(8).pow(2) };
+		max(500, complexity.saturating_mul(iteration_count(exp_len, exp_head, 16)))
+	} else if config.modexp_eip2565_pricing {
+		let complexity = max_len.div_ceil(8).pow(2);
+		max(200, complexity.saturating_mul(iteration_count(exp_len, exp_head, 8)) / 3)
+	} else {
+		let complexity = if max_len <= 64 {
+			max_len.pow(2)
+		} else if max_len <= 1024 {
+			max_len.pow(2) / 4 + 96 * max_len - 3072
+		} else {
+			max_len.pow(2) / 16 + 480 * max_len - 199_680
+		};
+		complexity.saturating_mul(iteration_count(exp_len, exp_head, 8)) / 20
+	};
+
+	u64::try_from(cost).unwrap_or(u64::MAX)
+}
+
+/// Index of the highest set bit of the exponent, counting `per_byte` bits
+/// for each byte past the first 32, and at least one.
+fn iteration_count(exp_len: u64, exp_head: U256, per_byte: u128) -> u128 {
+	let head_bits = exp_head.bits().saturating_sub(1) as u128;
+	let count = if exp_len <= 32 {
+		head_bits
+	} else {
+		per_byte.saturating_mul(u128::from(exp_len - 32)).saturating_add(head_bits)
+	};
+	max(count, 1)
+}
+
+/// Length held by a 32-byte word, saturated to `u64`.
+fn length(word: &[u8]) -> u64 {
+	let length = U256::from_big_endian(word);
+	if length > U256::from(u64::MAX) {
+		u64::MAX
+	} else {
+		length.low_u64()
+	}
+}
+
+fn to_usize(len: u64) -> usize {
+	usize::try_from(len).unwrap_or(usize::MAX)
+}
+
+/// `len` bytes of `input` from `offset`, right-padded with zeros.
+fn read(input: &[u8], offset: u64, len: usize) -> Vec<u8> {
+	let mut data = vec![0; len];
+	let start = min(to_usize(offset), input.len());
+	let end = min(start.saturating_add(len), input.len());
+	data[..end - start].copy_from_slice(&input[start..end]);
+	data
+}
+
+#[cfg(test)]
+mod tests {
+	use super::modexp;
+	use crate::precompile::{run_with_config, PrecompileFailure};
+	use crate::{Config, ExitError};
+
+	#[test]
+	fn test_modexp() {
+		// Fermat's little theorem: 3 ^ (p - 1) % p = 1 (EIP-198).
+		let input = hex::decode(concat!(
+			"0000000000000000000000000000000000000000000000000000000000000001",
+			"0000000000000000000000000000000000000000000000000000000000000020",
+			"0000000000000000000000000000000000000000000000000000000000000020",
+			"03",
+			"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+			"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+		))
+		.unwrap();
+
+		for (config, cost) in [(Config::byzantium(), 13056), (Config::berlin(), 1360), (Config::osaka(), 4080)] {
+			let output = run_with_config(modexp, &input, 100_000, &config).unwrap();
+			assert_eq!(output.cost, cost);
+			assert_eq!(hex::encode(output.output), format!("{:064x}", 1));
+		}
+	}
+
+	#[test]
+	fn test_modexp_bounds() {
+		// A 1025-byte modulus is priced before EIP-7823, and rejected after.
+		let mut input = vec![0; 96];
+		input[94..96].copy_from_slice(&[0x04, 0x01]);
+
+		let output = run_with_config(modexp, &input, 100_000, &Config::cancun()).unwrap();
+		assert_eq!((output.cost, output.output.len()), (5547, 1025));
+		assert_eq!(
+			run_with_config(modexp, &input, 100_000, &Config::osaka()).unwrap_err(),
+			PrecompileFailure::from(ExitError::InvalidPrecompileInput)
+		);
+		assert!(run_with_config(modexp, &input, 5546, &Config::cancun()).is_err());
+	}
+}
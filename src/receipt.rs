@@ -0,0 +1,147 @@
+//! Transaction receipts and logs bloom.
+
+use alloc::vec::Vec;
+use rlp::{Encodable, RlpStream};
+use sha3::{Digest, Keccak256};
+use crate::backend::Log;
+
+/// The 2048-bit logs bloom filter of a receipt or block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bloom(pub [u8; 256]);
+
+impl Default for Bloom {
+	fn default() -> Self {
+		Self([0; 256])
+	}
+}
+
+impl Bloom {
+	/// Bloom of the addresses and topics of `logs`.
+	#[must_use]
+	pub fn from_logs(logs: &[Log]) -> Self {
+		let mut bloom = Self::default();
+		for log in logs {
+			bloom.accrue_log(log);
+		}
+		bloom
+	}
+
+	/// Add the address and topics of a log.
+	pub fn accrue_log(&mut self, log: &Log) {
+		self.accrue(log.address.as_bytes());
+		for topic in &log.topics {
+			self.accrue(topic.as_bytes());
+		}
+	}
+
+	/// Add an input, setting the three bits picked by its Keccak-256 hash.
+	pub fn accrue(&mut self, input: &[u8]) {
+		for (byte, mask) in Self::bits(input) {
+			self.0[byte] |= mask;
+		}
+	}
+
+	/// Whether all three bits of an input are set.
+	#[must_use]
+	pub fn contains_input(&self, input: &[u8]) -> bool {
+		Self::bits(input).iter().all(|&(byte, mask)| self.0[byte] & mask == mask)
+	}
+
+	/// Merge another bloom into this one.
+	pub fn accrue_bloom(&mut self, other: &Self) {
+		for (byte, other) in self.0.iter_mut().zip(other.0.iter()) {
+			*byte |= other;
+		}
+	}
+
+	/// Byte and mask of each of the three bits of an input: the low 11 bits
+	/// of the first three byte pairs of its hash, counted from the end.
+	fn bits(input: &[u8]) -> [(usize, u8); 3] {
+		let hash = Keccak256::digest(input);
+		let bit = |i: usize| (usize::from(hash[i]) << 8 | usize::from(hash[i + 1])) & 0x7ff;
+		[bit(0), bit(2), bit(4)].map(|bit| (255 - bit / 8, 1 << (bit % 8)))
+	}
+}
+
+impl Encodable for Log {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		s.append(&self.address.as_bytes());
+		s.begin_list(self.topics.len());
+		for topic in &self.topics {
+			s.append(&topic.as_bytes());
+		}
+		s.append(&self.data);
+	}
+}
+
+/// Receipt of a transaction (EIP-658).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Receipt {
+	/// Whether the transaction succeeded.
+	pub status: bool,
+	/// Gas used in the block up to and including the transaction.
+	pub cumulative_gas_used: u64,
+	/// Bloom of `logs`.
+	pub logs_bloom: Bloom,
+	/// Logs emitted by the transaction.
+	pub logs: Vec<Log>,
+}
+
+impl Receipt {
+	/// Create a receipt, computing the bloom of `logs`.
+	#[must_use]
+	pub fn new(status: bool, cumulative_gas_used: u64, logs: Vec<Log>) -> Self {
+		Self { status, cumulative_gas_used, logs_bloom: Bloom::from_logs(&logs), logs }
+	}
+
+	/// Encoding of the receipt for the receipts trie: the RLP list, prefixed
+	/// with the transaction type unless it is a legacy transaction (EIP-2718).
+	#[must_use]
+	pub fn encode(&self, tx_type: u8) -> Vec<u8> {
+		let mut encoded = Vec::new();
+		if tx_type != 0 {
+			encoded.push(tx_type);
+		}
+		encoded.extend_from_slice(&rlp::encode(self));
+		encoded
+	}
+}
+
+impl Encodable for Receipt {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4);
+		s.append(&u8::from(self.status));
+		s.append(&self.cumulative_gas_used);
+		s.append(&self.logs_bloom.0.as_slice());
+		s.append_list(&self.logs);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec;
+	use super::{Bloom, Receipt};
+	use crate::backend::Log;
+	use crate::{H160, H256};
+
+	#[test]
+	fn test_bloom() {
+		let log = Log { address: H160::repeat_byte(0xaa), topics: vec![H256::repeat_byte(0xbb)], data: vec![1] };
+		let bloom = Bloom::from_logs(&[log]);
+
+		assert!(bloom.contains_input(H160::repeat_byte(0xaa).as_bytes()));
+		assert!(bloom.contains_input(H256::repeat_byte(0xbb).as_bytes()));
+		assert!(bloom.0.iter().map(|byte| byte.count_ones()).sum::<u32>() <= 6);
+		assert_eq!(Bloom::from_logs(&[]), Bloom::default());
+	}
+
+	#[test]
+	fn test_receipt_encode() {
+		let encoded = Receipt::new(true, 21_000, vec![]).encode(2);
+
+		assert_eq!(encoded.len(), 268);
+		assert_eq!(&encoded[..11], &[0x02, 0xf9, 0x01, 0x08, 0x01, 0x82, 0x52, 0x08, 0xb9, 0x01, 0x00]);
+		assert_eq!(encoded[267], 0xc0);
+	}
+}